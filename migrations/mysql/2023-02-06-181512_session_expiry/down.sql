@@ -0,0 +1,4 @@
+ALTER TABLE sessions
+  DROP COLUMN created_on,
+  DROP COLUMN last_seen,
+  DROP COLUMN expires_on;
@@ -0,0 +1,6 @@
+ALTER TABLE sessions
+  DROP COLUMN id,
+  DROP INDEX sessions_session_key,
+  ADD PRIMARY KEY (session_key),
+  DROP COLUMN user_agent,
+  DROP COLUMN ip_address;
@@ -0,0 +1,8 @@
+-- session keys shouldn't end up in pages listing sessions, so they get a
+-- separate id to refer to them by
+ALTER TABLE sessions
+  DROP PRIMARY KEY,
+  ADD COLUMN id INTEGER PRIMARY KEY AUTO_INCREMENT FIRST,
+  ADD UNIQUE sessions_session_key (session_key),
+  ADD COLUMN user_agent VARCHAR(512),
+  ADD COLUMN ip_address VARCHAR(45);
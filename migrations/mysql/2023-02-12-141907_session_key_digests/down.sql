@@ -0,0 +1,4 @@
+DELETE FROM sessions;
+ALTER TABLE sessions
+  CHANGE COLUMN key_digest session_key BINARY(32) NOT NULL,
+  RENAME INDEX sessions_key_digest TO sessions_session_key;
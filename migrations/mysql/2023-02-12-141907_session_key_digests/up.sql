@@ -0,0 +1,7 @@
+-- only a SHA-256 digest of the session key is stored from now on, so a leaked
+-- database can't be used to log in. The existing plaintext keys can't be told
+-- apart from digests, so all sessions are invalidated.
+DELETE FROM sessions;
+ALTER TABLE sessions
+  CHANGE COLUMN session_key key_digest BINARY(32) NOT NULL,
+  RENAME INDEX sessions_session_key TO sessions_key_digest;
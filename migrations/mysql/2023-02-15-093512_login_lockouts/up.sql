@@ -0,0 +1,14 @@
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+-- logins that were refused for too many failed attempts, kept so that
+-- administrators can see who is being targeted. user_id is NULL when a
+-- client address was locked out rather than an account.
+CREATE TABLE lockouts (
+  id INTEGER PRIMARY KEY AUTO_INCREMENT,
+  user_id INTEGER,
+  ip_address VARCHAR(45),
+  failed_attempts INTEGER NOT NULL,
+  locked_on DATETIME NOT NULL,
+  locked_until DATETIME NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+CREATE INDEX lockouts_locked_on ON lockouts (locked_on);
@@ -0,0 +1,8 @@
+CREATE TABLE password_resets (
+  id INTEGER PRIMARY KEY AUTO_INCREMENT,
+  token_digest BINARY(32) UNIQUE NOT NULL,
+  user_id INTEGER NOT NULL,
+  expires_on DATETIME NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+CREATE INDEX password_resets_expires_on ON password_resets (expires_on);
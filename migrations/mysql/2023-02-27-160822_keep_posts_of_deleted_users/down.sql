@@ -0,0 +1,2 @@
+DELETE FROM posts WHERE author IS NULL;
+ALTER TABLE posts MODIFY author INTEGER NOT NULL;
@@ -0,0 +1,2 @@
+-- posts of deleted accounts can be kept without an author
+ALTER TABLE posts MODIFY author INTEGER NULL;
@@ -0,0 +1,2 @@
+DROP INDEX users_email_canonical ON users;
+DROP INDEX users_username_canonical ON users;
@@ -0,0 +1,22 @@
+-- The secret is stored as soon as setup starts, but only asked for at login
+-- once the user has confirmed it with a first code. The last used time step
+-- keeps a code from being accepted twice.
+ALTER TABLE users ADD COLUMN totp_secret VARBINARY(20);
+ALTER TABLE users ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT;
+CREATE TABLE recovery_codes (
+  id INTEGER PRIMARY KEY AUTO_INCREMENT,
+  user_id INTEGER NOT NULL,
+  code_digest BINARY(32) NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+CREATE INDEX recovery_codes_user_id ON recovery_codes (user_id);
+-- a password that was entered correctly, waiting for the second factor
+CREATE TABLE login_challenges (
+  id INTEGER PRIMARY KEY AUTO_INCREMENT,
+  token_digest BINARY(32) UNIQUE NOT NULL,
+  user_id INTEGER NOT NULL,
+  expires_on DATETIME NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+CREATE INDEX login_challenges_expires_on ON login_challenges (expires_on);
@@ -0,0 +1,3 @@
+-- bcrypt hashes are always 60 characters, Argon2id ones are longer and grow
+-- with the number of digits in their parameters
+ALTER TABLE users MODIFY password VARCHAR(255) NOT NULL;
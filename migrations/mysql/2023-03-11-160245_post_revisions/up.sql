@@ -0,0 +1,13 @@
+ALTER TABLE posts ADD COLUMN edited_on DATETIME;
+-- every version of a post before its latest edit, the latest one is the post
+-- itself; created_on is when the version was written, not when it was replaced
+CREATE TABLE post_revisions (
+  id INTEGER PRIMARY KEY AUTO_INCREMENT,
+  post_id INTEGER NOT NULL,
+  created_on DATETIME NOT NULL,
+  title TEXT NOT NULL,
+  description TEXT NOT NULL,
+  content MEDIUMTEXT NOT NULL,
+  FOREIGN KEY (post_id) REFERENCES posts(id)
+);
+CREATE INDEX post_revisions_post_id ON post_revisions (post_id);
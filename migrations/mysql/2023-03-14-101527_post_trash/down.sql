@@ -0,0 +1,2 @@
+DROP INDEX posts_deleted_on ON posts;
+ALTER TABLE posts DROP COLUMN deleted_on;
@@ -0,0 +1 @@
+DROP INDEX posts_created_on_id ON posts;
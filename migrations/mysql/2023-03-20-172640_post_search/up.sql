@@ -0,0 +1,3 @@
+-- used by MATCH ... AGAINST on the search page, it has to list the same
+-- columns as the query does
+CREATE FULLTEXT INDEX posts_search ON posts (title, description, content);
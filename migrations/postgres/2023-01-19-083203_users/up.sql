@@ -0,0 +1,6 @@
+CREATE TABLE users (
+  id SERIAL PRIMARY KEY,
+  username VARCHAR(255) UNIQUE NOT NULL,
+  password VARCHAR(60) NOT NULL,
+  email VARCHAR(255) NOT NULL
+)
@@ -0,0 +1,4 @@
+CREATE TABLE sessions (
+  session_key BYTEA PRIMARY KEY NOT NULL,
+  user_id INTEGER NOT NULL
+);
@@ -0,0 +1,9 @@
+CREATE TABLE posts (
+  id SERIAL PRIMARY KEY,
+  author INTEGER NOT NULL,
+  created_on TIMESTAMP NOT NULL,
+  title TEXT NOT NULL,
+  description TEXT NOT NULL,
+  content TEXT NOT NULL,
+  FOREIGN KEY (author) REFERENCES users(id)
+)
@@ -0,0 +1,7 @@
+-- sessions from before expiry was tracked would otherwise never expire
+DELETE FROM sessions;
+ALTER TABLE sessions
+  ADD COLUMN created_on TIMESTAMP NOT NULL,
+  ADD COLUMN last_seen TIMESTAMP NOT NULL,
+  ADD COLUMN expires_on TIMESTAMP NOT NULL;
+CREATE INDEX sessions_expires_on ON sessions (expires_on);
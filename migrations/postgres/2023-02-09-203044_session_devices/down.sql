@@ -0,0 +1,6 @@
+ALTER TABLE sessions
+  DROP COLUMN id,
+  DROP CONSTRAINT sessions_session_key,
+  DROP COLUMN user_agent,
+  DROP COLUMN ip_address;
+ALTER TABLE sessions ADD PRIMARY KEY (session_key);
@@ -0,0 +1,8 @@
+-- session keys shouldn't end up in pages listing sessions, so they get a
+-- separate id to refer to them by
+ALTER TABLE sessions DROP CONSTRAINT sessions_pkey;
+ALTER TABLE sessions
+  ADD COLUMN id SERIAL PRIMARY KEY,
+  ADD CONSTRAINT sessions_session_key UNIQUE (session_key),
+  ADD COLUMN user_agent VARCHAR(512),
+  ADD COLUMN ip_address VARCHAR(45);
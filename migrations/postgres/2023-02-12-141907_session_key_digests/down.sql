@@ -0,0 +1,3 @@
+DELETE FROM sessions;
+ALTER TABLE sessions RENAME CONSTRAINT sessions_key_digest TO sessions_session_key;
+ALTER TABLE sessions RENAME COLUMN key_digest TO session_key;
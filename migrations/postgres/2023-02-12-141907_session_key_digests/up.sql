@@ -0,0 +1,6 @@
+-- only a SHA-256 digest of the session key is stored from now on, so a leaked
+-- database can't be used to log in. The existing plaintext keys can't be told
+-- apart from digests, so all sessions are invalidated.
+DELETE FROM sessions;
+ALTER TABLE sessions RENAME COLUMN session_key TO key_digest;
+ALTER TABLE sessions RENAME CONSTRAINT sessions_session_key TO sessions_key_digest;
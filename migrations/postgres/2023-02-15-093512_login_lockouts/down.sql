@@ -0,0 +1,2 @@
+DROP TABLE lockouts;
+ALTER TABLE users DROP COLUMN is_admin;
@@ -0,0 +1,13 @@
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+-- logins that were refused for too many failed attempts, kept so that
+-- administrators can see who is being targeted. user_id is NULL when a
+-- client address was locked out rather than an account.
+CREATE TABLE lockouts (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER REFERENCES users(id),
+  ip_address VARCHAR(45),
+  failed_attempts INTEGER NOT NULL,
+  locked_on TIMESTAMP NOT NULL,
+  locked_until TIMESTAMP NOT NULL
+);
+CREATE INDEX lockouts_locked_on ON lockouts (locked_on);
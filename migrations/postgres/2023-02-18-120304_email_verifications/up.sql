@@ -0,0 +1,11 @@
+-- accounts from before e-mail addresses were verified are trusted as they are
+ALTER TABLE users ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE users SET email_verified = TRUE;
+-- like session keys, only digests of the tokens sent out are stored
+CREATE TABLE email_verifications (
+  id SERIAL PRIMARY KEY,
+  token_digest BYTEA UNIQUE NOT NULL,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  expires_on TIMESTAMP NOT NULL
+);
+CREATE INDEX email_verifications_expires_on ON email_verifications (expires_on);
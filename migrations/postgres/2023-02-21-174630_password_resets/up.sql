@@ -0,0 +1,7 @@
+CREATE TABLE password_resets (
+  id SERIAL PRIMARY KEY,
+  token_digest BYTEA UNIQUE NOT NULL,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  expires_on TIMESTAMP NOT NULL
+);
+CREATE INDEX password_resets_expires_on ON password_resets (expires_on);
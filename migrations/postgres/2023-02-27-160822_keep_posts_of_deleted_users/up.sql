@@ -0,0 +1,2 @@
+-- posts of deleted accounts can be kept without an author
+ALTER TABLE posts ALTER COLUMN author DROP NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE users DROP COLUMN email_canonical;
+ALTER TABLE users DROP COLUMN username_canonical;
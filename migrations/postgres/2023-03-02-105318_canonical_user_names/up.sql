@@ -0,0 +1,6 @@
+-- Lookups go through these, the application fills them in with the Unicode
+-- normalized, lowercase form of the username and e-mail address. SQL can't
+-- normalize Unicode, so the application fills in existing rows right before
+-- the next migration makes them unique.
+ALTER TABLE users ADD COLUMN username_canonical VARCHAR(255) NOT NULL DEFAULT '';
+ALTER TABLE users ADD COLUMN email_canonical VARCHAR(255) NOT NULL DEFAULT '';
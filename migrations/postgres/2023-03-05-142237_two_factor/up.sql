@@ -0,0 +1,20 @@
+-- The secret is stored as soon as setup starts, but only asked for at login
+-- once the user has confirmed it with a first code. The last used time step
+-- keeps a code from being accepted twice.
+ALTER TABLE users ADD COLUMN totp_secret BYTEA;
+ALTER TABLE users ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT;
+CREATE TABLE recovery_codes (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  code_digest BYTEA NOT NULL
+);
+CREATE INDEX recovery_codes_user_id ON recovery_codes (user_id);
+-- a password that was entered correctly, waiting for the second factor
+CREATE TABLE login_challenges (
+  id SERIAL PRIMARY KEY,
+  token_digest BYTEA UNIQUE NOT NULL,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  expires_on TIMESTAMP NOT NULL
+);
+CREATE INDEX login_challenges_expires_on ON login_challenges (expires_on);
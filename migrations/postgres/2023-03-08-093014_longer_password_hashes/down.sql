@@ -0,0 +1,2 @@
+-- fails while any Argon2id hashes are left, they don't fit
+ALTER TABLE users ALTER COLUMN password TYPE VARCHAR(60);
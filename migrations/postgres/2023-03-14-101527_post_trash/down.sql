@@ -0,0 +1,2 @@
+DROP INDEX posts_deleted_on;
+ALTER TABLE posts DROP COLUMN deleted_on;
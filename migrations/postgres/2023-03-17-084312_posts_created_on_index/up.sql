@@ -0,0 +1,2 @@
+-- the order posts are paged through in, see PostCursor
+CREATE INDEX posts_created_on_id ON posts (created_on, id);
@@ -0,0 +1,6 @@
+CREATE TABLE users (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  username VARCHAR(255) UNIQUE NOT NULL,
+  password VARCHAR(60) NOT NULL,
+  email VARCHAR(255) NOT NULL
+)
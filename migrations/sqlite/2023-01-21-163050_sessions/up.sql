@@ -0,0 +1,4 @@
+CREATE TABLE sessions (
+  session_key BLOB PRIMARY KEY NOT NULL,
+  user_id INTEGER NOT NULL
+);
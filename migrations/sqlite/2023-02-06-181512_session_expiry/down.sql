@@ -0,0 +1,5 @@
+DROP TABLE sessions;
+CREATE TABLE sessions (
+  session_key BLOB PRIMARY KEY NOT NULL,
+  user_id INTEGER NOT NULL
+);
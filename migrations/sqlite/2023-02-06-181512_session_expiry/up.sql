@@ -0,0 +1,11 @@
+-- SQLite can't add NOT NULL columns without a default, and sessions from
+-- before expiry was tracked would otherwise never expire anyway
+DROP TABLE sessions;
+CREATE TABLE sessions (
+  session_key BLOB PRIMARY KEY NOT NULL,
+  user_id INTEGER NOT NULL,
+  created_on TIMESTAMP NOT NULL,
+  last_seen TIMESTAMP NOT NULL,
+  expires_on TIMESTAMP NOT NULL
+);
+CREATE INDEX sessions_expires_on ON sessions (expires_on);
@@ -0,0 +1,12 @@
+CREATE TABLE old_sessions (
+  session_key BLOB PRIMARY KEY NOT NULL,
+  user_id INTEGER NOT NULL,
+  created_on TIMESTAMP NOT NULL,
+  last_seen TIMESTAMP NOT NULL,
+  expires_on TIMESTAMP NOT NULL
+);
+INSERT INTO old_sessions (session_key, user_id, created_on, last_seen, expires_on)
+  SELECT session_key, user_id, created_on, last_seen, expires_on FROM sessions;
+DROP TABLE sessions;
+ALTER TABLE old_sessions RENAME TO sessions;
+CREATE INDEX sessions_expires_on ON sessions (expires_on);
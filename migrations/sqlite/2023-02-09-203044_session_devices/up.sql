@@ -0,0 +1,18 @@
+-- session keys shouldn't end up in pages listing sessions, so they get a
+-- separate id to refer to them by. SQLite can't change the primary key of an
+-- existing table, so it has to be rebuilt.
+CREATE TABLE new_sessions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  session_key BLOB UNIQUE NOT NULL,
+  user_id INTEGER NOT NULL,
+  created_on TIMESTAMP NOT NULL,
+  last_seen TIMESTAMP NOT NULL,
+  expires_on TIMESTAMP NOT NULL,
+  user_agent VARCHAR(512),
+  ip_address VARCHAR(45)
+);
+INSERT INTO new_sessions (session_key, user_id, created_on, last_seen, expires_on)
+  SELECT session_key, user_id, created_on, last_seen, expires_on FROM sessions;
+DROP TABLE sessions;
+ALTER TABLE new_sessions RENAME TO sessions;
+CREATE INDEX sessions_expires_on ON sessions (expires_on);
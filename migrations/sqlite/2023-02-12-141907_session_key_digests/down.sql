@@ -0,0 +1,2 @@
+DELETE FROM sessions;
+ALTER TABLE sessions RENAME COLUMN key_digest TO session_key;
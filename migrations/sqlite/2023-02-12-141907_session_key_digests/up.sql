@@ -0,0 +1,5 @@
+-- only a SHA-256 digest of the session key is stored from now on, so a leaked
+-- database can't be used to log in. The existing plaintext keys can't be told
+-- apart from digests, so all sessions are invalidated.
+DELETE FROM sessions;
+ALTER TABLE sessions RENAME COLUMN session_key TO key_digest;
@@ -0,0 +1,3 @@
+DROP TABLE lockouts;
+-- needs SQLite 3.35 or newer
+ALTER TABLE users DROP COLUMN is_admin;
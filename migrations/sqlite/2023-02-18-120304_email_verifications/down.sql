@@ -0,0 +1,3 @@
+DROP TABLE email_verifications;
+-- needs SQLite 3.35 or newer
+ALTER TABLE users DROP COLUMN email_verified;
@@ -0,0 +1,7 @@
+CREATE TABLE password_resets (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  token_digest BLOB UNIQUE NOT NULL,
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  expires_on TIMESTAMP NOT NULL
+);
+CREATE INDEX password_resets_expires_on ON password_resets (expires_on);
@@ -0,0 +1,2 @@
+-- needs SQLite 3.35 or newer
+ALTER TABLE email_verifications DROP COLUMN new_email;
@@ -0,0 +1,2 @@
+-- set when the link confirms a change of address rather than a registration
+ALTER TABLE email_verifications ADD COLUMN new_email VARCHAR(255);
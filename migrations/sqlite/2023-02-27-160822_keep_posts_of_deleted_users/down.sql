@@ -0,0 +1,13 @@
+CREATE TABLE posts_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  author INTEGER NOT NULL,
+  created_on TIMESTAMP NOT NULL,
+  title TEXT NOT NULL,
+  description TEXT NOT NULL,
+  content TEXT NOT NULL,
+  FOREIGN KEY (author) REFERENCES users(id)
+);
+INSERT INTO posts_new SELECT id, author, created_on, title, description, content FROM posts
+  WHERE author IS NOT NULL;
+DROP TABLE posts;
+ALTER TABLE posts_new RENAME TO posts;
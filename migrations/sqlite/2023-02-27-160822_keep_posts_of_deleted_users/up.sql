@@ -0,0 +1,14 @@
+-- posts of deleted accounts can be kept without an author, and SQLite can't
+-- drop NOT NULL from a column, so the table is rebuilt
+CREATE TABLE posts_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  author INTEGER,
+  created_on TIMESTAMP NOT NULL,
+  title TEXT NOT NULL,
+  description TEXT NOT NULL,
+  content TEXT NOT NULL,
+  FOREIGN KEY (author) REFERENCES users(id)
+);
+INSERT INTO posts_new SELECT id, author, created_on, title, description, content FROM posts;
+DROP TABLE posts;
+ALTER TABLE posts_new RENAME TO posts;
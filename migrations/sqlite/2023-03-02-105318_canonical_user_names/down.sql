@@ -0,0 +1,3 @@
+-- needs SQLite 3.35 or newer
+ALTER TABLE users DROP COLUMN email_canonical;
+ALTER TABLE users DROP COLUMN username_canonical;
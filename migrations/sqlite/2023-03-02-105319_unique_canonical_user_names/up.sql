@@ -0,0 +1,2 @@
+CREATE UNIQUE INDEX users_username_canonical ON users (username_canonical);
+CREATE UNIQUE INDEX users_email_canonical ON users (email_canonical);
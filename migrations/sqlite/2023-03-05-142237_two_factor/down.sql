@@ -0,0 +1,6 @@
+DROP TABLE login_challenges;
+DROP TABLE recovery_codes;
+-- needs SQLite 3.35 or newer
+ALTER TABLE users DROP COLUMN totp_last_step;
+ALTER TABLE users DROP COLUMN totp_enabled;
+ALTER TABLE users DROP COLUMN totp_secret;
@@ -0,0 +1,3 @@
+-- SQLite doesn't enforce the length of VARCHAR columns, so Argon2id hashes
+-- already fit into the password column as it is
+SELECT 1;
@@ -0,0 +1,3 @@
+DROP TABLE post_revisions;
+-- needs SQLite 3.35 or newer
+ALTER TABLE posts DROP COLUMN edited_on;
@@ -0,0 +1,12 @@
+ALTER TABLE posts ADD COLUMN edited_on TIMESTAMP;
+-- every version of a post before its latest edit, the latest one is the post
+-- itself; created_on is when the version was written, not when it was replaced
+CREATE TABLE post_revisions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  post_id INTEGER NOT NULL REFERENCES posts(id),
+  created_on TIMESTAMP NOT NULL,
+  title TEXT NOT NULL,
+  description TEXT NOT NULL,
+  content TEXT NOT NULL
+);
+CREATE INDEX post_revisions_post_id ON post_revisions (post_id);
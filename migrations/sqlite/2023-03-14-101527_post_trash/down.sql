@@ -0,0 +1,3 @@
+DROP INDEX posts_deleted_on;
+-- needs SQLite 3.35 or newer
+ALTER TABLE posts DROP COLUMN deleted_on;
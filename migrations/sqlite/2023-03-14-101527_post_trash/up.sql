@@ -0,0 +1,4 @@
+-- posts in the trash are hidden everywhere but the trash page of their author,
+-- and purged for good once they have been there long enough
+ALTER TABLE posts ADD COLUMN deleted_on TIMESTAMP;
+CREATE INDEX posts_deleted_on ON posts (deleted_on);
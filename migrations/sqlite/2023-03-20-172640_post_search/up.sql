@@ -0,0 +1,2 @@
+-- only MySQL gets a FULLTEXT index, posts are searched with LIKE here
+SELECT 1;
@@ -0,0 +1,253 @@
+use rocket::{get, http::CookieJar, post, FromForm, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    csrf::{CsrfForm, CsrfProtected},
+    models::canonical,
+    register::username_holder,
+    validation::{validate_email, validate_password, validate_username},
+    verify::send_email_change_email,
+};
+
+#[derive(Serialize, Debug)]
+struct AccountLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    email: Option<String>,
+    error: Option<String>,
+    success: Option<String>,
+}
+
+impl AccountLayoutContext {
+    pub fn new(state: &State<SharedState>, jar: &CookieJar) -> Result<AccountLayoutContext, Error> {
+        let (email, error) = match state.get_valid_user_info(jar)? {
+            Some(user) => (Some(user.email), None),
+            None => (None, Some("You need to log in first!".to_string())),
+        };
+
+        Ok(AccountLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            email,
+            error,
+            success: None,
+        })
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_success(mut self, success: Option<String>) -> Self {
+        self.success = success;
+        self
+    }
+}
+
+#[get("/account")]
+pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "account",
+        AccountLayoutContext::new(state, jar)?,
+    ))
+}
+
+#[derive(FromForm)]
+pub struct ChangePasswordForm {
+    pub csrf_token: String,
+    pub current_password: String,
+    pub password: String,
+    pub password_repeat: String,
+}
+
+impl CsrfProtected for ChangePasswordForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/account/password", data = "<data>")]
+pub fn post_password(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    data: CsrfForm<ChangePasswordForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some((session, user)) = state.get_valid_session(jar)? else {
+            break 'requirements Some("You need to log in first!".to_string())
+        };
+
+        if data.current_password.is_empty()
+            || data.password.is_empty()
+            || data.password_repeat.is_empty()
+        {
+            break 'requirements Some("All fields are required!".to_string());
+        }
+        if data.password != data.password_repeat {
+            break 'requirements Some("Password attempts don't match!".to_string());
+        }
+        if !state
+            .password_hasher()
+            .verify(&data.current_password, &user.password)?
+        {
+            break 'requirements Some("Current password is incorrect!".to_string());
+        }
+        if let Err(error) = validate_password(state.config(), &data.password) {
+            break 'requirements Some(error);
+        }
+
+        let hashed_password = state.password_hasher().hash(&data.password)?;
+        state
+            .database()
+            .set_user_password(user.id, &hashed_password)?;
+
+        // whoever knew the old password shouldn't stay logged in with it
+        state
+            .database()
+            .remove_user_sessions_except(user.id, session.id)?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "account",
+            AccountLayoutContext::new(state, jar)?.with_error(Some(error_message)),
+        ));
+    }
+
+    Ok(Template::render(
+        "account",
+        AccountLayoutContext::new(state, jar)?.with_success(Some(
+            "Password changed, all other devices have been logged out!".to_string(),
+        )),
+    ))
+}
+
+#[derive(FromForm)]
+pub struct ChangeEmailForm {
+    pub csrf_token: String,
+    pub email: String,
+    pub current_password: String,
+}
+
+impl CsrfProtected for ChangeEmailForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/account/email", data = "<data>")]
+pub fn post_email(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    data: CsrfForm<ChangeEmailForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!".to_string())
+        };
+
+        if data.email.is_empty() || data.current_password.is_empty() {
+            break 'requirements Some("All fields are required!".to_string());
+        }
+        // whoever gets hold of a session could otherwise take the account over
+        // by moving it to their own address and resetting the password
+        if !state
+            .password_hasher()
+            .verify(&data.current_password, &user.password)?
+        {
+            break 'requirements Some("Current password is incorrect!".to_string());
+        }
+        if canonical(&data.email) == user.email_canonical {
+            break 'requirements Some("That is already your e-mail address!".to_string());
+        }
+        if let Err(error) = validate_email(&data.email) {
+            break 'requirements Some(error);
+        }
+
+        // A taken address gets the same response as a free one, like when
+        // registering. The account only switches over once the link sent to
+        // the new address is opened, so a typo can't lock anyone out.
+        if state.database().get_user_by_email(&data.email)?.is_none() {
+            send_email_change_email(state, &user, &data.email)?;
+        }
+
+        None
+    } {
+        return Ok(Template::render(
+            "account",
+            AccountLayoutContext::new(state, jar)?.with_error(Some(error_message)),
+        ));
+    }
+
+    Ok(Template::render(
+        "account",
+        AccountLayoutContext::new(state, jar)?.with_success(Some(
+            "Check your inbox, the address changes once you open the link we've sent to it."
+                .to_string(),
+        )),
+    ))
+}
+
+#[derive(FromForm)]
+pub struct ChangeUsernameForm {
+    pub csrf_token: String,
+    pub username: String,
+}
+
+impl CsrfProtected for ChangeUsernameForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/account/username", data = "<data>")]
+pub fn post_username(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    data: CsrfForm<ChangeUsernameForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!".to_string())
+        };
+
+        if data.username.is_empty() {
+            break 'requirements Some("All fields are required!".to_string());
+        }
+        if data.username == user.username {
+            break 'requirements Some("That is already your username!".to_string());
+        }
+        if let Err(error) = validate_username(state.config(), &data.username) {
+            break 'requirements Some(error);
+        }
+        // only changing the case of the name is fine, it's still the user's own
+        if username_holder(state, &data.username)?.is_some_and(|holder| holder.id != user.id) {
+            break 'requirements Some("Username already in use!".to_string());
+        }
+
+        match state.database().set_user_username(user.id, &data.username) {
+            // somebody else took the name in the meantime
+            Err(error) if error.is_unique_violation() => {
+                break 'requirements Some("Username already in use!".to_string());
+            }
+            result => result?,
+        }
+
+        None
+    } {
+        return Ok(Template::render(
+            "account",
+            AccountLayoutContext::new(state, jar)?.with_error(Some(error_message)),
+        ));
+    }
+
+    Ok(Template::render(
+        "account",
+        AccountLayoutContext::new(state, jar)?.with_success(Some("Username changed!".to_string())),
+    ))
+}
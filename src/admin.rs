@@ -0,0 +1,89 @@
+use rocket::{get, http::CookieJar, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    models::LockoutWithUsername,
+};
+
+// how many of the most recent lockouts are listed
+const LOCKOUT_COUNT: i64 = 100;
+
+#[derive(Serialize, Debug)]
+struct LockoutData {
+    account: Option<String>,
+    ip_address: String,
+    failed_attempts: i32,
+    human_readable_lock_time: String,
+    human_readable_unlock_time: String,
+    active: bool,
+}
+
+impl LockoutData {
+    fn from_lockout(LockoutWithUsername { lockout, username }: LockoutWithUsername) -> Self {
+        Self {
+            account: username,
+            ip_address: lockout
+                .ip_address
+                .unwrap_or_else(|| "Unknown address".to_string()),
+            failed_attempts: lockout.failed_attempts,
+            human_readable_lock_time: lockout.locked_on.format("%d. %m. %Y. %H:%M:%S").to_string(),
+            human_readable_unlock_time: lockout
+                .locked_until
+                .format("%d. %m. %Y. %H:%M:%S")
+                .to_string(),
+            active: lockout.locked_until > chrono::offset::Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct LockoutsLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    lockouts: Vec<LockoutData>,
+    error: Option<String>,
+}
+
+impl LockoutsLayoutContext {
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+    ) -> Result<LockoutsLayoutContext, Error> {
+        let is_admin = state
+            .get_valid_user_info(jar)?
+            .map_or(false, |user| user.is_admin);
+
+        let (lockouts, error) = if is_admin {
+            (
+                state
+                    .database()
+                    .get_latest_x_lockouts(LOCKOUT_COUNT)?
+                    .into_iter()
+                    .map(LockoutData::from_lockout)
+                    .collect(),
+                None,
+            )
+        } else {
+            (
+                Vec::new(),
+                Some("Only administrators can see this page!".to_string()),
+            )
+        };
+
+        Ok(LockoutsLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            lockouts,
+            error,
+        })
+    }
+}
+
+#[get("/admin/lockouts")]
+pub fn lockouts(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "lockouts",
+        LockoutsLayoutContext::new(state, jar)?,
+    ))
+}
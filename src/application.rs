@@ -1,23 +1,60 @@
+use std::{convert::Infallible, net::IpAddr, sync::Arc};
+
 use bcrypt::BcryptError;
-use diesel::result::Error as DieselError;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use rand::{rngs::StdRng, Fill, SeedableRng};
 use rocket::{
-    http::{Cookie, CookieJar},
-    Responder, State,
+    http::{Cookie, CookieJar, Status},
+    request::{FromRequest, Outcome},
+    Request, Responder, State,
 };
 use rocket_dyn_templates::{context, Template};
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 
-use crate::{database::Database, models::User};
+use crate::{
+    config::Config,
+    csrf,
+    database::Database,
+    mail::Mailer,
+    models::{NewSession, Session, User},
+    password::PasswordHasher,
+    throttle::LoginThrottle,
+};
 
 #[derive(Debug)]
 pub enum Error {
     MissingDatabaseUrl,
+    UnsupportedDatabaseUrl,
     UnableToConnectToDatabase,
+    InvalidDatabaseConfiguration(&'static str),
+    DatabaseUnavailable,
+    Migration(String),
+    PendingMigrations,
     Diesel(DieselError),
     Bcrypt(BcryptError),
+    PasswordHash(String),
     Rand(rand::Error),
+    Mail(String),
+    Export(String),
+    QrCode(String),
     PostHasInvalidUserId,
     InvalidPostId,
+    PostDeleted,
+}
+
+impl Error {
+    // a row would have duplicated a value in a UNIQUE column, usually because
+    // another request got there first
+    pub fn is_unique_violation(&self) -> bool {
+        matches!(
+            self,
+            Error::Diesel(DieselError::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                _
+            ))
+        )
+    }
 }
 
 impl From<DieselError> for Error {
@@ -34,7 +71,7 @@ impl From<BcryptError> for Error {
 
 #[derive(Responder)]
 pub struct ErrorResponder {
-    result: Template,
+    result: (Status, Template),
 }
 
 impl From<Error> for ErrorResponder {
@@ -43,25 +80,72 @@ impl From<Error> for ErrorResponder {
             Reference(&'a str),
             String(String),
         }
+
+        // not really a server error, the request can simply be retried later
+        if let Error::DatabaseUnavailable = value {
+            return Self {
+                result: (
+                    Status::ServiceUnavailable,
+                    Template::render("database_unavailable", context! {}),
+                ),
+            };
+        }
+
+        // a post in the trash is gone, one that was removed for good can't be
+        // told apart from one that never existed
+        let status = match value {
+            Error::PostDeleted => Status::Gone,
+            Error::InvalidPostId => Status::NotFound,
+            _ => Status::InternalServerError,
+        };
+
         let message: ErrorMessage = match value {
             Error::MissingDatabaseUrl => {
                 ErrorMessage::Reference("Missing database URL in the server configuration")
             }
+            Error::UnsupportedDatabaseUrl => ErrorMessage::Reference(
+                "The database URL in the server configuration uses an unsupported backend",
+            ),
             Error::UnableToConnectToDatabase => {
                 ErrorMessage::Reference("Unable to connect to the provided database URL")
             }
+            Error::InvalidDatabaseConfiguration(variable) => ErrorMessage::String(format!(
+                "Invalid value for {variable} in the server configuration"
+            )),
+            Error::DatabaseUnavailable => {
+                ErrorMessage::Reference("The database is temporarily unavailable")
+            }
+            Error::Migration(migration_error) => {
+                ErrorMessage::String(format!("Failed to migrate the database: {migration_error}"))
+            }
+            Error::PendingMigrations => {
+                ErrorMessage::Reference("The database schema is missing some migrations")
+            }
             Error::PostHasInvalidUserId => ErrorMessage::Reference("Post has an invalid user id"),
             Error::InvalidPostId => ErrorMessage::Reference("Invalid post ID"),
+            Error::PostDeleted => ErrorMessage::Reference("This post has been deleted"),
             Error::Bcrypt(bcrypt_error) => {
                 ErrorMessage::String(format!("Failed on a bcrypt function: {bcrypt_error}"))
             }
+            Error::PasswordHash(hash_error) => {
+                ErrorMessage::String(format!("Failed to hash a password: {hash_error}"))
+            }
             Error::Diesel(diesel_error) => {
                 ErrorMessage::String(format!("Failed on a database query: {diesel_error}"))
             }
             Error::Rand(rand_error) => ErrorMessage::String(format!("Rand error: {rand_error}")),
+            Error::Mail(mail_error) => {
+                ErrorMessage::String(format!("Failed to send an e-mail: {mail_error}"))
+            }
+            Error::Export(export_error) => {
+                ErrorMessage::String(format!("Failed to export the account: {export_error}"))
+            }
+            Error::QrCode(qr_code_error) => {
+                ErrorMessage::String(format!("Failed to create the QR code: {qr_code_error}"))
+            }
         };
 
-        let result = match message {
+        let template = match message {
             ErrorMessage::Reference(message) => {
                 Template::render("server_error", context! {error_message: message})
             }
@@ -70,74 +154,294 @@ impl From<Error> for ErrorResponder {
             }
         };
 
+        let result = (status, template);
+
         Self { result }
     }
 }
 
-pub struct SharedStateData {
-    database: Database,
+// random bytes for session keys and other tokens that must not be guessable
+pub fn generate_key() -> Result<Vec<u8>, Error> {
+    let mut key = vec![0u8; 32];
+    key.try_fill(&mut StdRng::from_entropy())
+        .map_err(Error::Rand)?;
+    Ok(key)
 }
 
-use std::sync::Mutex;
-pub type SharedState = Mutex<SharedStateData>;
+// Only this digest of a session key or token ends up in the database, so
+// whoever can read the tables still can't impersonate anyone.
+pub fn token_digest(token: &[u8]) -> Vec<u8> {
+    Sha256::digest(token).to_vec()
+}
 
-impl SharedStateData {
-    pub fn new() -> Result<SharedStateData, Error> {
-        Ok(SharedStateData {
-            database: Database::new()?,
-        })
+const SESSION_COOKIE: &str = "session-key";
+
+pub struct SharedState {
+    database: Arc<dyn Database>,
+    config: Config,
+    secure_cookies: bool,
+    login_throttle: Arc<LoginThrottle>,
+    password_reset_throttle: Arc<LoginThrottle>,
+    mailer: Arc<dyn Mailer>,
+    password_hasher: PasswordHasher,
+}
+
+impl SharedState {
+    pub fn new(
+        database: Box<dyn Database>,
+        mailer: Box<dyn Mailer>,
+        password_hasher: PasswordHasher,
+        config: Config,
+        tls_enabled: bool,
+    ) -> SharedState {
+        SharedState {
+            database: database.into(),
+            mailer: mailer.into(),
+            password_hasher,
+            secure_cookies: config.secure_session_cookie.unwrap_or(tls_enabled),
+            login_throttle: Arc::new(LoginThrottle::new(&config)),
+            password_reset_throttle: Arc::new(LoginThrottle::for_password_resets(&config)),
+            config,
+        }
+    }
+
+    pub fn database(&self) -> &dyn Database {
+        self.database.as_ref()
+    }
+
+    // for work done outside of requests, like the periodic cleanup
+    pub fn database_handle(&self) -> Arc<dyn Database> {
+        self.database.clone()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn mailer(&self) -> &dyn Mailer {
+        self.mailer.as_ref()
+    }
+
+    // for e-mails sent outside of requests
+    pub fn mailer_handle(&self) -> Arc<dyn Mailer> {
+        self.mailer.clone()
+    }
+
+    pub fn password_hasher(&self) -> &PasswordHasher {
+        &self.password_hasher
+    }
+
+    pub fn login_throttle(&self) -> &LoginThrottle {
+        &self.login_throttle
+    }
+
+    pub fn login_throttle_handle(&self) -> Arc<LoginThrottle> {
+        self.login_throttle.clone()
+    }
+
+    pub fn password_reset_throttle(&self) -> &LoginThrottle {
+        &self.password_reset_throttle
     }
 
-    pub fn database(&mut self) -> &mut Database {
-        &mut self.database
+    pub fn password_reset_throttle_handle(&self) -> Arc<LoginThrottle> {
+        self.password_reset_throttle.clone()
     }
 
-    fn get_valid_user_info_from_session_cookie(
-        &mut self,
-        cookie: &Cookie<'static>,
-    ) -> Result<Option<User>, Error> {
-        hex::decode(cookie.value())
-            .ok()
-            // attempt to get a session with that session key
-            .and_then(|decoded_key| self.database().get_session_by_key(decoded_key).transpose())
-            // if the session exists, attempt to get a user with the session's user_id
-            .and_then(|maybe_session| {
-                maybe_session
-                    .map(|session| self.database().get_user_by_id(session.user_id).transpose())
-                    .transpose()
-            })
-            // merge the two Results into one
-            .map(Result::flatten)
-            .transpose()
+    // All cookies are set with these attributes, so that renewing a cookie
+    // doesn't lose any of them.
+    pub fn cookie(&self, name: &'static str, value: String) -> Cookie<'static> {
+        Cookie::build(name, value)
+            .path("/")
+            .http_only(true)
+            .same_site(self.config.session_cookie_same_site.into())
+            .secure(self.secure_cookies)
+            .finish()
     }
 
-    pub fn get_valid_user_info(&mut self, jar: &CookieJar) -> Result<Option<User>, Error> {
-        // get a not-yet-sent session key if there is one
+    fn session_cookie(&self, value: String) -> Cookie<'static> {
+        let mut cookie = self.cookie(SESSION_COOKIE, value);
+        cookie.set_max_age(rocket::time::Duration::seconds(
+            self.config.session_lifetime.into(),
+        ));
+        cookie
+    }
+
+    pub fn add_session_cookie(&self, jar: &CookieJar, session_key: &[u8]) {
+        let cookie = self.session_cookie(hex::encode(session_key));
+        if self.config.private_session_cookie {
+            jar.add_private(cookie);
+        } else {
+            jar.add(cookie);
+        }
+    }
+
+    pub fn remove_session_cookie(&self, jar: &CookieJar) {
+        let cookie = self.session_cookie(String::new());
+        if self.config.private_session_cookie {
+            jar.remove_private(cookie);
+        } else {
+            jar.remove(cookie);
+        }
+    }
+
+    // the session key sent by the browser, if there is a well-formed one
+    pub fn session_key_from_cookie(&self, jar: &CookieJar) -> Option<Vec<u8>> {
+        let cookie = if self.config.private_session_cookie {
+            jar.get_private(SESSION_COOKIE)
+        } else {
+            jar.get(SESSION_COOKIE).cloned()
+        };
+        cookie.and_then(|cookie| hex::decode(cookie.value()).ok())
+    }
+
+    pub fn new_session<'a>(
+        &self,
+        session_key: &[u8],
+        user_id: i32,
+        client: &'a ClientInfo,
+    ) -> NewSession<'a> {
+        let now = chrono::offset::Utc::now().naive_utc();
+        NewSession {
+            key_digest: token_digest(session_key),
+            user_id,
+            created_on: now,
+            last_seen: now,
+            expires_on: now + self.config.session_lifetime(),
+            user_agent: client.user_agent.as_deref(),
+            ip_address: client.ip_address.as_deref(),
+        }
+    }
+
+    // Expired sessions are removed right away, valid ones get their expiry
+    // pushed back since the user is still active.
+    fn check_session_expiry(
+        &self,
+        jar: &CookieJar,
+        session_key: &[u8],
+        session: Session,
+    ) -> Result<Option<Session>, Error> {
+        let now = chrono::offset::Utc::now().naive_utc();
+
+        if session.expires_on <= now {
+            self.database().remove_session_by_key(session.key_digest)?;
+            return Ok(None);
+        }
+
+        if now - session.last_seen < self.config.session_renewal_interval() {
+            return Ok(Some(session));
+        }
+
+        let expires_on = now + self.config.session_lifetime();
+        self.database()
+            .renew_session(session.key_digest.clone(), now, expires_on)?;
+        // the browser has to be told about the new expiry as well
+        self.add_session_cookie(jar, session_key);
+
+        Ok(Some(Session {
+            last_seen: now,
+            expires_on,
+            ..session
+        }))
+    }
+
+    fn get_valid_session_from_session_key(
+        &self,
+        jar: &CookieJar,
+        session_key: &[u8],
+    ) -> Result<Option<(Session, User)>, Error> {
+        let key_digest = token_digest(session_key);
+        let Some(session) = self.database().get_session_by_key(key_digest)? else {
+            return Ok(None);
+        };
+
+        let Some(session) = self.check_session_expiry(jar, session_key, session)? else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .database()
+            .get_user_by_id(session.user_id)?
+            .map(|user| (session, user)))
+    }
+
+    pub fn get_valid_session(&self, jar: &CookieJar) -> Result<Option<(Session, User)>, Error> {
+        // get a not-yet-sent session key if there is one, pending cookies
+        // aren't encrypted yet even when private cookies are used
         let pending = jar
-            .get_pending("session-key")
-            .map(|cookie| self.get_valid_user_info_from_session_cookie(&cookie))
+            .get_pending(SESSION_COOKIE)
+            .and_then(|cookie| hex::decode(cookie.value()).ok())
+            .map(|session_key| self.get_valid_session_from_session_key(jar, &session_key))
             .transpose()?
             .flatten();
-        Ok(jar
-            .get("session-key")
-            .map(|cookie| self.get_valid_user_info_from_session_cookie(cookie))
+        Ok(self
+            .session_key_from_cookie(jar)
+            .map(|session_key| self.get_valid_session_from_session_key(jar, &session_key))
             .transpose()?
             .flatten()
             // try the pending one if the received one is invalid/doesn't exist
-            .or_else(|| pending))
+            .or(pending))
+    }
+
+    pub fn get_valid_user_info(&self, jar: &CookieJar) -> Result<Option<User>, Error> {
+        Ok(self.get_valid_session(jar)?.map(|(_, user)| user))
+    }
+}
+
+// Information about the device a request came from, recorded with sessions
+// so that users can tell their logins apart.
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+// the user_agent column can't hold more than this
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+// Failed logins are counted per address, so the address mustn't come from a
+// header unless the configuration says that a proxy sets it.
+fn client_ip_address(request: &Request<'_>) -> Option<IpAddr> {
+    let header = request
+        .rocket()
+        .state::<SharedState>()
+        .and_then(|state| state.config().client_ip_header.as_deref());
+    match header {
+        Some(header) => request
+            .headers()
+            .get_one(header)
+            .and_then(|value| value.trim().parse().ok()),
+        None => request.remote().map(|remote| remote.ip()),
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientInfo {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ClientInfo {
+            user_agent: request
+                .headers()
+                .get_one("User-Agent")
+                .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LENGTH).collect()),
+            ip_address: client_ip_address(request).map(|ip_address| ip_address.to_string()),
+        })
     }
 }
 
 #[derive(Serialize, Debug)]
 pub struct BaseLayoutContext {
     username: Option<String>,
+    is_admin: bool,
+    csrf_token: String,
 }
 
 impl BaseLayoutContext {
     pub fn new(state: &State<SharedState>, jar: &CookieJar) -> Result<BaseLayoutContext, Error> {
-        let user_info = state.lock().unwrap().get_valid_user_info(jar)?;
+        let user_info = state.get_valid_user_info(jar)?;
         Ok(BaseLayoutContext {
+            is_admin: user_info.as_ref().map_or(false, |info| info.is_admin),
             username: user_info.map(|info| info.username),
+            csrf_token: csrf::token(state, jar)?,
         })
     }
 }
@@ -0,0 +1,167 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use rocket::{get, http::CookieJar, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    index::ShortPostData,
+};
+
+// midnight at the start of the month, None for months that don't exist
+fn first_day_of(year: i32, month: u32) -> Option<NaiveDateTime> {
+    NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+}
+
+fn month_url(year: i32, month: u32) -> String {
+    format!("/archive/{year}/{month}")
+}
+
+#[derive(Serialize, Debug)]
+struct MonthData {
+    name: String,
+    count: usize,
+    url: String,
+}
+
+#[derive(Serialize, Debug)]
+struct ArchiveLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    // newest first
+    months: Vec<MonthData>,
+}
+
+impl ArchiveLayoutContext {
+    pub fn new(state: &State<SharedState>, jar: &CookieJar) -> Result<ArchiveLayoutContext, Error> {
+        let months = state
+            .database()
+            .get_post_counts_by_month()?
+            .into_iter()
+            .rev()
+            .filter_map(|counted| {
+                let month = u32::try_from(counted.month).ok()?;
+                Some(MonthData {
+                    name: first_day_of(counted.year, month)?
+                        .format("%B %Y")
+                        .to_string(),
+                    count: usize::try_from(counted.count).ok()?,
+                    url: month_url(counted.year, month),
+                })
+            })
+            .collect();
+
+        Ok(ArchiveLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            months,
+        })
+    }
+}
+
+#[get("/archive")]
+pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "archive",
+        ArchiveLayoutContext::new(state, jar)?,
+    ))
+}
+
+#[derive(Serialize, Debug)]
+struct DayData {
+    human_readable_date: String,
+    count: usize,
+    posts: Vec<ShortPostData>,
+}
+
+#[derive(Serialize, Debug)]
+struct ArchiveMonthLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    name: String,
+    count: usize,
+    // newest first, only days with posts
+    days: Vec<DayData>,
+    // the closest months that have any posts
+    previous_month_url: Option<String>,
+    next_month_url: Option<String>,
+    error: Option<String>,
+}
+
+impl ArchiveMonthLayoutContext {
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+        year: i32,
+        month: u32,
+    ) -> Result<ArchiveMonthLayoutContext, Error> {
+        let mut context = ArchiveMonthLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            name: String::default(),
+            count: 0,
+            days: Vec::new(),
+            previous_month_url: None,
+            next_month_url: None,
+            error: None,
+        };
+
+        let start = first_day_of(year, month);
+        let end = match month {
+            12 => year
+                .checked_add(1)
+                .and_then(|next_year| first_day_of(next_year, 1)),
+            _ => month
+                .checked_add(1)
+                .and_then(|next_month| first_day_of(year, next_month)),
+        };
+        let (Some(start), Some(end)) = (start, end) else {
+            context.error = Some("No such month!".to_string());
+            return Ok(context);
+        };
+        context.name = start.format("%B %Y").to_string();
+
+        let posts = state.database().get_posts_created_between(start, end)?;
+        context.count = posts.len();
+
+        // the posts are newest first already, so each day's posts are together
+        for post in posts {
+            let date = post.post.created_on.date();
+            let human_readable_date = date.format("%d. %m. %Y.").to_string();
+            let post = ShortPostData::from_post(post)?;
+            match context.days.last_mut() {
+                Some(day) if day.human_readable_date == human_readable_date => {
+                    day.count += 1;
+                    day.posts.push(post);
+                }
+                _ => context.days.push(DayData {
+                    human_readable_date,
+                    count: 1,
+                    posts: vec![post],
+                }),
+            }
+        }
+
+        context.previous_month_url = state
+            .database()
+            .get_latest_post_time_before(start)?
+            .map(|time| month_url(time.year(), time.month()));
+        context.next_month_url = state
+            .database()
+            .get_earliest_post_time_from(end)?
+            .map(|time| month_url(time.year(), time.month()));
+
+        Ok(context)
+    }
+}
+
+#[get("/archive/<year>/<month>")]
+pub fn get_month(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    year: i32,
+    month: u32,
+) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "archive_month",
+        ArchiveMonthLayoutContext::new(state, jar, year, month)?,
+    ))
+}
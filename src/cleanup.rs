@@ -0,0 +1,55 @@
+use rocket::{
+    fairing::AdHoc,
+    tokio::{self, task, time},
+};
+
+use crate::application::SharedState;
+
+// Periodically removes rows that are no longer of any use, like expired
+// sessions and verification links, so that they don't pile up in the database
+// forever. Posts that have been in the trash for long enough go too, and so do
+// failed logins and reset requests that have been forgiven.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Database cleanup", |rocket| {
+        Box::pin(async move {
+            let state = rocket
+                .state::<SharedState>()
+                .expect("shared state is managed");
+            let database = state.database_handle();
+            let throttles = [
+                state.login_throttle_handle(),
+                state.password_reset_throttle_handle(),
+            ];
+            let mut interval = time::interval(state.config().cleanup_interval());
+            let deleted_post_retention = state.config().deleted_post_retention();
+
+            tokio::spawn(async move {
+                loop {
+                    interval.tick().await;
+                    let now = chrono::offset::Utc::now().naive_utc();
+                    for throttle in &throttles {
+                        throttle.forget_stale(now);
+                    }
+
+                    // database calls are blocking, keep them off the async workers
+                    let database = database.clone();
+                    let result = task::spawn_blocking(move || {
+                        let now = chrono::offset::Utc::now().naive_utc();
+                        database.remove_expired_sessions(now)?;
+                        database.remove_expired_email_verifications(now)?;
+                        database.remove_expired_password_resets(now)?;
+                        database.remove_expired_login_challenges(now)?;
+                        database.remove_posts_deleted_before(now - deleted_post_retention)
+                    })
+                    .await;
+
+                    match result {
+                        Ok(Ok(_)) => {}
+                        Ok(Err(error)) => log::warn!("Failed to purge expired rows: {error:?}"),
+                        Err(error) => log::warn!("Purge task failed: {error}"),
+                    }
+                }
+            });
+        })
+    })
+}
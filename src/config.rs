@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use rocket::http::SameSite;
+use serde::Deserialize;
+
+use crate::{mail::MailerKind, password::PasswordAlgorithm};
+
+// Application settings, read from Rocket.toml or ROCKET_* environment
+// variables alongside Rocket's own configuration. All durations are in seconds.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct Config {
+    // how long a session stays valid without any activity
+    pub session_lifetime: u32,
+    // sessions are renewed at most this often, so that not every single
+    // request has to write to the database
+    pub session_renewal_interval: u32,
+    // how often expired rows are purged from the database
+    pub cleanup_interval: u32,
+    // SameSite attribute of the session cookie
+    pub session_cookie_same_site: SameSitePolicy,
+    // encrypts the session cookie with Rocket's secret_key
+    pub private_session_cookie: bool,
+    // the Secure attribute is set when Rocket itself serves TLS, this allows
+    // forcing it, e.g. when TLS is terminated by a reverse proxy
+    pub secure_session_cookie: Option<bool>,
+    // failed logins per account or address before further attempts are delayed
+    pub login_attempts_before_delay: u32,
+    // failed logins per account or address before it is locked out
+    pub login_attempts_before_lockout: u32,
+    pub login_lockout_duration: u32,
+    // the same for asking for password reset links, per e-mail address or
+    // client address, with the same lockout duration
+    pub password_reset_requests_before_delay: u32,
+    pub password_reset_requests_before_lockout: u32,
+    // The header a reverse proxy puts the client's address in, e.g.
+    // X-Real-IP. Anyone can send such a header, so by default the address of
+    // the connection is used instead.
+    pub client_ip_header: Option<String>,
+    // lengths are counted in characters
+    pub username_min_length: usize,
+    pub username_max_length: usize,
+    // allowed in usernames besides ASCII letters and digits
+    pub username_allowed_symbols: String,
+    pub password_min_length: usize,
+    // can't go past bcrypt's limit of 72 bytes either way
+    pub password_max_length: usize,
+    // what new password hashes are made with, existing ones are rehashed
+    // when their owner logs in
+    pub password_hash_algorithm: PasswordAlgorithm,
+    pub bcrypt_cost: u32,
+    // memory in KiB, number of passes and lanes
+    pub argon2_memory_cost: u32,
+    pub argon2_time_cost: u32,
+    pub argon2_parallelism: u32,
+    // where the application can be reached, used for links in e-mails
+    pub public_url: String,
+    // how long the link for confirming an e-mail address stays valid
+    pub email_verification_lifetime: u32,
+    // logging in before confirming the address sends a new link at most this
+    // often
+    pub email_verification_resend_interval: u32,
+    // how long the link for choosing a new password stays valid
+    pub password_reset_lifetime: u32,
+    // how long the second step of a login can take after the password
+    pub two_factor_login_lifetime: u32,
+    // posts on each page of the front page
+    pub posts_per_page: u32,
+    // how long deleted posts stay in the trash before they are purged
+    pub deleted_post_retention: u32,
+    // the name authenticator apps list the account under
+    pub totp_issuer: String,
+    pub mailer: MailerKind,
+    pub mail_from: String,
+    pub mail_directory: String,
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_starttls: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSitePolicy {
+    Strict,
+    Lax,
+}
+
+impl From<SameSitePolicy> for SameSite {
+    fn from(value: SameSitePolicy) -> Self {
+        match value {
+            SameSitePolicy::Strict => SameSite::Strict,
+            SameSitePolicy::Lax => SameSite::Lax,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            session_lifetime: 14 * 24 * 60 * 60,
+            session_renewal_interval: 60,
+            cleanup_interval: 60 * 60,
+            // Strict would log users out when following a link from another site
+            session_cookie_same_site: SameSitePolicy::Lax,
+            private_session_cookie: false,
+            secure_session_cookie: None,
+            login_attempts_before_delay: 3,
+            login_attempts_before_lockout: 10,
+            login_lockout_duration: 15 * 60,
+            password_reset_requests_before_delay: 2,
+            password_reset_requests_before_lockout: 5,
+            client_ip_header: None,
+            username_min_length: 3,
+            username_max_length: 32,
+            username_allowed_symbols: "_-.".to_string(),
+            password_min_length: 8,
+            password_max_length: 72,
+            // the minimum OWASP recommends for Argon2id
+            password_hash_algorithm: PasswordAlgorithm::Argon2id,
+            bcrypt_cost: bcrypt::DEFAULT_COST,
+            argon2_memory_cost: 19 * 1024,
+            argon2_time_cost: 2,
+            argon2_parallelism: 1,
+            public_url: "http://localhost:8000".to_string(),
+            email_verification_lifetime: 24 * 60 * 60,
+            email_verification_resend_interval: 10 * 60,
+            password_reset_lifetime: 60 * 60,
+            two_factor_login_lifetime: 5 * 60,
+            posts_per_page: 10,
+            deleted_post_retention: 30 * 24 * 60 * 60,
+            totp_issuer: "Seminarski".to_string(),
+            mailer: MailerKind::Stdout,
+            mail_from: "Seminarski <noreply@localhost>".to_string(),
+            mail_directory: "mail".to_string(),
+            smtp_host: None,
+            smtp_port: None,
+            smtp_username: None,
+            smtp_password: None,
+            smtp_starttls: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn session_lifetime(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.session_lifetime.into())
+    }
+
+    pub fn session_renewal_interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.session_renewal_interval.into())
+    }
+
+    pub fn login_lockout_duration(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.login_lockout_duration.into())
+    }
+
+    pub fn email_verification_lifetime(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.email_verification_lifetime.into())
+    }
+
+    pub fn email_verification_resend_interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.email_verification_resend_interval.into())
+    }
+
+    pub fn password_reset_lifetime(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.password_reset_lifetime.into())
+    }
+
+    pub fn two_factor_login_lifetime(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.two_factor_login_lifetime.into())
+    }
+
+    pub fn deleted_post_retention(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.deleted_post_retention.into())
+    }
+
+    pub fn cleanup_interval(&self) -> Duration {
+        // tokio refuses zero length intervals
+        Duration::from_secs(self.cleanup_interval.max(1).into())
+    }
+}
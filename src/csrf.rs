@@ -0,0 +1,109 @@
+use std::ops::Deref;
+
+use rocket::{
+    catch,
+    data::{self, Data, FromData},
+    form::{self, Errors, Form, FromForm},
+    http::{CookieJar, Status},
+    outcome::try_outcome,
+    Request,
+};
+use rocket_dyn_templates::{context, Template};
+
+use crate::application::{generate_key, Error, SharedState};
+
+const CSRF_COOKIE: &str = "csrf-token";
+
+// Forms that change something have to carry the token from the csrf-token
+// cookie, which other sites can neither read nor set (double-submit cookie).
+pub trait CsrfProtected {
+    fn csrf_token(&self) -> &str;
+}
+
+// for forms that consist of nothing but the token, like the logout button
+#[derive(FromForm)]
+pub struct CsrfTokenForm {
+    pub csrf_token: String,
+}
+
+impl CsrfProtected for CsrfTokenForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+// Returns the token the rendered forms have to submit, creating it if the
+// browser doesn't have one yet.
+pub fn token(state: &SharedState, jar: &CookieJar) -> Result<String, Error> {
+    if let Some(cookie) = jar.get_pending(CSRF_COOKIE) {
+        if !cookie.value().is_empty() {
+            return Ok(cookie.value().to_string());
+        }
+    }
+
+    let token = hex::encode(generate_key()?);
+    jar.add(state.cookie(CSRF_COOKIE, token.clone()));
+    Ok(token)
+}
+
+// compares in constant time so the token can't be guessed byte by byte
+fn tokens_match(expected: &str, submitted: &str) -> bool {
+    !expected.is_empty()
+        && expected.len() == submitted.len()
+        && expected
+            .bytes()
+            .zip(submitted.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+// A Form that is only accepted if it carries a valid CSRF token, anything else
+// is refused with 403 Forbidden.
+pub struct CsrfForm<T>(T);
+
+impl<T> Deref for CsrfForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: FromForm<'r> + CsrfProtected> FromData<'r> for CsrfForm<T> {
+    type Error = Errors<'r>;
+
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let form = match Form::<T>::from_data(request, data).await {
+            // a form without the token is refused like one with a wrong token,
+            // whatever else is wrong with it
+            data::Outcome::Failure((_, errors))
+                if errors.iter().any(|error| error.is_for_exactly("csrf_token")) =>
+            {
+                return data::Outcome::Failure((Status::Forbidden, errors));
+            }
+            outcome => try_outcome!(outcome).into_inner(),
+        };
+
+        let valid = request.cookies().get(CSRF_COOKIE).map_or(false, |cookie| {
+            tokens_match(cookie.value(), form.csrf_token())
+        });
+
+        if valid {
+            data::Outcome::Success(CsrfForm(form))
+        } else {
+            data::Outcome::Failure((
+                Status::Forbidden,
+                form::Error::validation("invalid CSRF token").into(),
+            ))
+        }
+    }
+}
+
+#[catch(403)]
+pub fn forbidden() -> (Status, Template) {
+    (
+        Status::Forbidden,
+        Template::render("forbidden", context! {}),
+    )
+}
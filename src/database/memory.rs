@@ -0,0 +1,901 @@
+use std::{cmp::Reverse, collections::BTreeMap, sync::Mutex};
+
+use chrono::{Datelike, NaiveDateTime};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+
+use super::Database;
+use crate::{
+    models::{
+        canonical, EmailVerification, Lockout, LockoutWithUsername, LoginChallenge, MonthPostCount,
+        NewEmailVerification, NewLockout, NewLoginChallenge, NewPasswordReset, NewPost,
+        NewRecoveryCode, NewSession, NewUser, PasswordReset, Post, PostCursor, PostEdit,
+        PostRevision, PostSearch, PostWithAuthor, RecoveryCode, Session, User,
+    },
+    Error,
+};
+
+#[derive(Default)]
+struct MemoryData {
+    users: Vec<User>,
+    sessions: Vec<Session>,
+    posts: Vec<Post>,
+    post_revisions: Vec<PostRevision>,
+    lockouts: Vec<Lockout>,
+    email_verifications: Vec<EmailVerification>,
+    password_resets: Vec<PasswordReset>,
+    login_challenges: Vec<LoginChallenge>,
+    recovery_codes: Vec<RecoveryCode>,
+
+    // ids are never reused, same as with AUTO_INCREMENT
+    last_user_id: i32,
+    last_session_id: i32,
+    last_post_id: i32,
+    last_post_revision_id: i32,
+    last_lockout_id: i32,
+    last_email_verification_id: i32,
+    last_password_reset_id: i32,
+    last_login_challenge_id: i32,
+    last_recovery_code_id: i32,
+}
+
+impl MemoryData {
+    // what the LEFT JOIN with users gives the SQL backends
+    fn with_author(&self, post: Post) -> PostWithAuthor {
+        let author_username = post.author.and_then(|author| {
+            self.users
+                .iter()
+                .find(|user| user.id == author)
+                .map(|user| user.username.clone())
+        });
+        PostWithAuthor {
+            post,
+            author_username,
+        }
+    }
+
+    // what the foreign key on post_revisions would insist on
+    fn remove_orphaned_revisions(&mut self) {
+        let posts = &self.posts;
+        self.post_revisions
+            .retain(|revision| posts.iter().any(|post| post.id == revision.post_id));
+    }
+}
+
+// Keeps everything in process memory and loses it on shutdown, meant for
+// tests and trying the application out without setting up a database.
+#[derive(Default)]
+pub struct MemoryDatabase {
+    data: Mutex<MemoryData>,
+}
+
+impl MemoryDatabase {
+    pub fn new() -> MemoryDatabase {
+        MemoryDatabase::default()
+    }
+
+    // mirrors the errors the SQL backends return for UNIQUE columns
+    fn unique_violation(message: &str) -> Error {
+        Error::Diesel(DieselError::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            Box::new(message.to_string()),
+        ))
+    }
+}
+
+impl Database for MemoryDatabase {
+    // there is no schema to keep up to date
+    fn has_pending_migrations(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn run_pending_migrations(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_user_by_id(&self, by_id: i32) -> Result<Option<User>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data.users.iter().find(|user| user.id == by_id).cloned())
+    }
+
+    fn get_user_by_username(&self, by_username: &str) -> Result<Option<User>, Error> {
+        let data = self.data.lock().unwrap();
+        let by_username = canonical(by_username);
+        Ok(data
+            .users
+            .iter()
+            .find(|user| user.username_canonical == by_username)
+            .cloned())
+    }
+
+    fn get_user_by_email(&self, by_email: &str) -> Result<Option<User>, Error> {
+        let data = self.data.lock().unwrap();
+        let by_email = canonical(by_email);
+        Ok(data
+            .users
+            .iter()
+            .find(|user| user.email_canonical == by_email)
+            .cloned())
+    }
+
+    fn get_session_by_key(&self, by_digest: Vec<u8>) -> Result<Option<Session>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .sessions
+            .iter()
+            .find(|session| session.key_digest == by_digest)
+            .cloned())
+    }
+
+    fn get_sessions_by_user_id(&self, by_user_id: i32) -> Result<Vec<Session>, Error> {
+        let data = self.data.lock().unwrap();
+        let mut sessions: Vec<Session> = data
+            .sessions
+            .iter()
+            .filter(|session| session.user_id == by_user_id)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| Reverse(session.last_seen));
+        Ok(sessions)
+    }
+
+    fn get_post_by_id(&self, by_id: i32) -> Result<Option<Post>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data.posts.iter().find(|post| post.id == by_id).cloned())
+    }
+
+    fn get_post_with_author_by_id(&self, by_id: i32) -> Result<Option<PostWithAuthor>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .posts
+            .iter()
+            .find(|post| post.id == by_id)
+            .map(|post| data.with_author(post.clone())))
+    }
+
+    fn get_posts_before(
+        &self,
+        before: Option<PostCursor>,
+        count: i64,
+    ) -> Result<Vec<PostWithAuthor>, Error> {
+        let data = self.data.lock().unwrap();
+        let mut posts: Vec<Post> = data
+            .posts
+            .iter()
+            .filter(|post| post.deleted_on.is_none())
+            .filter(|post| before.map_or(true, |before| post.cursor() < before))
+            .cloned()
+            .collect();
+        posts.sort_by_key(|post| Reverse(post.cursor()));
+        posts.truncate(usize::try_from(count).unwrap_or(0));
+        Ok(posts
+            .into_iter()
+            .map(|post| data.with_author(post))
+            .collect())
+    }
+
+    fn get_posts_after(&self, after: PostCursor, count: i64) -> Result<Vec<PostWithAuthor>, Error> {
+        let data = self.data.lock().unwrap();
+        let mut posts: Vec<Post> = data
+            .posts
+            .iter()
+            .filter(|post| post.deleted_on.is_none() && post.cursor() > after)
+            .cloned()
+            .collect();
+        posts.sort_by_key(Post::cursor);
+        posts.truncate(usize::try_from(count).unwrap_or(0));
+        Ok(posts
+            .into_iter()
+            .rev()
+            .map(|post| data.with_author(post))
+            .collect())
+    }
+
+    fn get_posts_created_between(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Vec<PostWithAuthor>, Error> {
+        let data = self.data.lock().unwrap();
+        let mut posts: Vec<Post> = data
+            .posts
+            .iter()
+            .filter(|post| post.deleted_on.is_none())
+            .filter(|post| from <= post.created_on && post.created_on < to)
+            .cloned()
+            .collect();
+        posts.sort_by_key(|post| Reverse(post.cursor()));
+        Ok(posts
+            .into_iter()
+            .map(|post| data.with_author(post))
+            .collect())
+    }
+
+    // matches like the LIKE fallback of the SQL backends does
+    fn search_posts(
+        &self,
+        search: &PostSearch,
+        before: Option<PostCursor>,
+        count: i64,
+    ) -> Result<Vec<PostWithAuthor>, Error> {
+        let data = self.data.lock().unwrap();
+        let contains_word = |post: &Post, word: &String| {
+            [&post.title, &post.description, &post.content]
+                .iter()
+                .any(|field| field.to_lowercase().contains(word.as_str()))
+        };
+
+        let mut posts: Vec<Post> = data
+            .posts
+            .iter()
+            .filter(|post| post.deleted_on.is_none())
+            .filter(|post| before.map_or(true, |before| post.cursor() < before))
+            .filter(|post| {
+                search
+                    .author
+                    .map_or(true, |author| post.author == Some(author))
+            })
+            .filter(|post| search.from.map_or(true, |from| from <= post.created_on))
+            .filter(|post| search.to.map_or(true, |to| post.created_on < to))
+            .filter(|post| search.words.iter().all(|word| contains_word(post, word)))
+            .cloned()
+            .collect();
+        posts.sort_by_key(|post| Reverse(post.cursor()));
+        posts.truncate(usize::try_from(count).unwrap_or(0));
+        Ok(posts
+            .into_iter()
+            .map(|post| data.with_author(post))
+            .collect())
+    }
+
+    fn get_post_counts_by_month(&self) -> Result<Vec<MonthPostCount>, Error> {
+        let data = self.data.lock().unwrap();
+        let mut counts = BTreeMap::new();
+        for post in data.posts.iter().filter(|post| post.deleted_on.is_none()) {
+            // months are 1 to 12, so this never skips a post
+            let Ok(month) = i32::try_from(post.created_on.month()) else {
+                continue;
+            };
+            *counts.entry((post.created_on.year(), month)).or_default() += 1;
+        }
+        Ok(counts
+            .into_iter()
+            .map(|((year, month), count)| MonthPostCount { year, month, count })
+            .collect())
+    }
+
+    fn get_latest_post_time_before(
+        &self,
+        before: NaiveDateTime,
+    ) -> Result<Option<NaiveDateTime>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .posts
+            .iter()
+            .filter(|post| post.deleted_on.is_none() && post.created_on < before)
+            .map(|post| post.created_on)
+            .max())
+    }
+
+    fn get_earliest_post_time_from(
+        &self,
+        from: NaiveDateTime,
+    ) -> Result<Option<NaiveDateTime>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .posts
+            .iter()
+            .filter(|post| post.deleted_on.is_none() && post.created_on >= from)
+            .map(|post| post.created_on)
+            .min())
+    }
+
+    fn get_posts_by_author(&self, by_author: i32) -> Result<Vec<Post>, Error> {
+        let data = self.data.lock().unwrap();
+        let mut posts: Vec<Post> = data
+            .posts
+            .iter()
+            .filter(|post| post.author == Some(by_author))
+            .cloned()
+            .collect();
+        posts.sort_by_key(|post| Reverse(post.created_on));
+        Ok(posts)
+    }
+
+    fn get_deleted_posts_by_author(&self, by_author: i32) -> Result<Vec<Post>, Error> {
+        let data = self.data.lock().unwrap();
+        let mut posts: Vec<Post> = data
+            .posts
+            .iter()
+            .filter(|post| post.author == Some(by_author) && post.deleted_on.is_some())
+            .cloned()
+            .collect();
+        posts.sort_by_key(|post| Reverse(post.deleted_on));
+        Ok(posts)
+    }
+
+    fn get_post_revisions(&self, by_post_id: i32) -> Result<Vec<PostRevision>, Error> {
+        let data = self.data.lock().unwrap();
+        // kept in the order they were added
+        Ok(data
+            .post_revisions
+            .iter()
+            .filter(|revision| revision.post_id == by_post_id)
+            .cloned()
+            .collect())
+    }
+
+    fn get_post_revisions_by_author(&self, by_author: i32) -> Result<Vec<PostRevision>, Error> {
+        let data = self.data.lock().unwrap();
+        let mut revisions: Vec<PostRevision> = data
+            .post_revisions
+            .iter()
+            .filter(|revision| {
+                data.posts
+                    .iter()
+                    .any(|post| post.id == revision.post_id && post.author == Some(by_author))
+            })
+            .cloned()
+            .collect();
+        // stable, so each post's revisions stay in the order they were added
+        revisions.sort_by_key(|revision| revision.post_id);
+        Ok(revisions)
+    }
+
+    fn get_latest_x_lockouts(&self, count: i64) -> Result<Vec<LockoutWithUsername>, Error> {
+        let data = self.data.lock().unwrap();
+        let mut lockouts = data.lockouts.clone();
+        lockouts.sort_by_key(|lockout| Reverse(lockout.locked_on));
+        lockouts.truncate(usize::try_from(count).unwrap_or(0));
+        Ok(lockouts
+            .into_iter()
+            .map(|lockout| {
+                let username = data
+                    .users
+                    .iter()
+                    .find(|user| Some(user.id) == lockout.user_id)
+                    .map(|user| user.username.clone());
+                LockoutWithUsername { lockout, username }
+            })
+            .collect())
+    }
+
+    fn get_email_verification_by_token(
+        &self,
+        by_digest: Vec<u8>,
+    ) -> Result<Option<EmailVerification>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .email_verifications
+            .iter()
+            .find(|verification| verification.token_digest == by_digest)
+            .cloned())
+    }
+
+    fn get_latest_email_verification_by_user_id(
+        &self,
+        by_user_id: i32,
+    ) -> Result<Option<EmailVerification>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .email_verifications
+            .iter()
+            .filter(|verification| verification.user_id == by_user_id)
+            .max_by_key(|verification| verification.expires_on)
+            .cloned())
+    }
+
+    fn get_password_reset_by_token(
+        &self,
+        by_digest: Vec<u8>,
+    ) -> Result<Option<PasswordReset>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .password_resets
+            .iter()
+            .find(|reset| reset.token_digest == by_digest)
+            .cloned())
+    }
+
+    fn get_login_challenge_by_token(
+        &self,
+        by_digest: Vec<u8>,
+    ) -> Result<Option<LoginChallenge>, Error> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .login_challenges
+            .iter()
+            .find(|challenge| challenge.token_digest == by_digest)
+            .cloned())
+    }
+
+    fn get_recovery_code_count(&self, by_user_id: i32) -> Result<i64, Error> {
+        let data = self.data.lock().unwrap();
+        let count = data
+            .recovery_codes
+            .iter()
+            .filter(|code| code.user_id == by_user_id)
+            .count();
+        Ok(i64::try_from(count).unwrap_or(i64::MAX))
+    }
+
+    fn create_user(&self, user: NewUser) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        if data
+            .users
+            .iter()
+            .any(|existing| existing.username_canonical == user.username_canonical)
+        {
+            return Err(Self::unique_violation(
+                "Duplicate entry for key 'users_username_canonical'",
+            ));
+        }
+        if data
+            .users
+            .iter()
+            .any(|existing| existing.email_canonical == user.email_canonical)
+        {
+            return Err(Self::unique_violation(
+                "Duplicate entry for key 'users_email_canonical'",
+            ));
+        }
+
+        data.last_user_id += 1;
+        let id = data.last_user_id;
+        data.users.push(User {
+            id,
+            username: user.username.to_string(),
+            password: user.password.to_string(),
+            email: user.email.to_string(),
+            is_admin: false,
+            email_verified: false,
+            username_canonical: user.username_canonical,
+            email_canonical: user.email_canonical,
+            totp_secret: None,
+            totp_enabled: false,
+            totp_last_step: None,
+        });
+        Ok(())
+    }
+
+    fn create_session(&self, session: NewSession) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        if data
+            .sessions
+            .iter()
+            .any(|existing| existing.key_digest == session.key_digest)
+        {
+            return Err(Self::unique_violation(
+                "Duplicate entry for key 'sessions_key_digest'",
+            ));
+        }
+
+        data.last_session_id += 1;
+        let id = data.last_session_id;
+        data.sessions.push(Session {
+            id,
+            key_digest: session.key_digest,
+            user_id: session.user_id,
+            created_on: session.created_on,
+            last_seen: session.last_seen,
+            expires_on: session.expires_on,
+            user_agent: session.user_agent.map(str::to_string),
+            ip_address: session.ip_address.map(str::to_string),
+        });
+        Ok(())
+    }
+
+    fn create_post(&self, post: NewPost) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.last_post_id += 1;
+        let id = data.last_post_id;
+        data.posts.push(Post {
+            id,
+            author: Some(post.author),
+            created_on: post.created_on,
+            title: post.title.to_string(),
+            description: post.description.to_string(),
+            content: post.content.to_string(),
+            edited_on: None,
+            deleted_on: None,
+        });
+        Ok(())
+    }
+
+    fn create_lockout(&self, lockout: NewLockout) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.last_lockout_id += 1;
+        let id = data.last_lockout_id;
+        data.lockouts.push(Lockout {
+            id,
+            user_id: lockout.user_id,
+            ip_address: lockout.ip_address.map(str::to_string),
+            failed_attempts: lockout.failed_attempts,
+            locked_on: lockout.locked_on,
+            locked_until: lockout.locked_until,
+        });
+        Ok(())
+    }
+
+    fn create_email_verification(&self, verification: NewEmailVerification) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        if data
+            .email_verifications
+            .iter()
+            .any(|existing| existing.token_digest == verification.token_digest)
+        {
+            return Err(Self::unique_violation(
+                "Duplicate entry for key 'token_digest'",
+            ));
+        }
+
+        data.last_email_verification_id += 1;
+        let id = data.last_email_verification_id;
+        data.email_verifications.push(EmailVerification {
+            id,
+            token_digest: verification.token_digest,
+            user_id: verification.user_id,
+            expires_on: verification.expires_on,
+            new_email: verification.new_email.map(str::to_string),
+        });
+        Ok(())
+    }
+
+    fn create_password_reset(&self, reset: NewPasswordReset) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        if data
+            .password_resets
+            .iter()
+            .any(|existing| existing.token_digest == reset.token_digest)
+        {
+            return Err(Self::unique_violation(
+                "Duplicate entry for key 'token_digest'",
+            ));
+        }
+
+        data.last_password_reset_id += 1;
+        let id = data.last_password_reset_id;
+        data.password_resets.push(PasswordReset {
+            id,
+            token_digest: reset.token_digest,
+            user_id: reset.user_id,
+            expires_on: reset.expires_on,
+        });
+        Ok(())
+    }
+
+    fn create_login_challenge(&self, challenge: NewLoginChallenge) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        if data
+            .login_challenges
+            .iter()
+            .any(|existing| existing.token_digest == challenge.token_digest)
+        {
+            return Err(Self::unique_violation(
+                "Duplicate entry for key 'token_digest'",
+            ));
+        }
+
+        data.last_login_challenge_id += 1;
+        let id = data.last_login_challenge_id;
+        data.login_challenges.push(LoginChallenge {
+            id,
+            token_digest: challenge.token_digest,
+            user_id: challenge.user_id,
+            expires_on: challenge.expires_on,
+        });
+        Ok(())
+    }
+
+    fn create_recovery_codes(&self, codes: Vec<NewRecoveryCode>) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        for code in codes {
+            data.last_recovery_code_id += 1;
+            let id = data.last_recovery_code_id;
+            data.recovery_codes.push(RecoveryCode {
+                id,
+                user_id: code.user_id,
+                code_digest: code.code_digest,
+            });
+        }
+        Ok(())
+    }
+
+    fn edit_post(&self, by_id: i32, edit: PostEdit) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        let Some(index) = data.posts.iter().position(|post| post.id == by_id) else {
+            return Err(Error::Diesel(DieselError::NotFound));
+        };
+
+        data.last_post_revision_id += 1;
+        let id = data.last_post_revision_id;
+        let post = &data.posts[index];
+        let revision = PostRevision {
+            id,
+            post_id: post.id,
+            created_on: post.last_changed_on(),
+            title: post.title.clone(),
+            description: post.description.clone(),
+            content: post.content.clone(),
+        };
+        data.post_revisions.push(revision);
+
+        let post = &mut data.posts[index];
+        post.title = edit.title.to_string();
+        post.description = edit.description.to_string();
+        post.content = edit.content.to_string();
+        post.edited_on = Some(edit.edited_on);
+        Ok(())
+    }
+
+    fn set_post_deleted_on(
+        &self,
+        by_id: i32,
+        deleted_on: Option<NaiveDateTime>,
+    ) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(post) = data.posts.iter_mut().find(|post| post.id == by_id) {
+            post.deleted_on = deleted_on;
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn set_user_admin(&self, by_id: i32, is_admin: bool) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        for user in &mut data.users {
+            if user.id == by_id {
+                user.is_admin = is_admin;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_user_email_verified(&self, by_id: i32) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        for user in &mut data.users {
+            if user.id == by_id {
+                user.email_verified = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_user_password(&self, by_id: i32, password: &str) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        for user in &mut data.users {
+            if user.id == by_id {
+                user.password = password.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    fn set_user_username(&self, by_id: i32, username: &str) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        let username_canonical = canonical(username);
+        if data.users.iter().any(|existing| {
+            existing.username_canonical == username_canonical && existing.id != by_id
+        }) {
+            return Err(Self::unique_violation(
+                "Duplicate entry for key 'users_username_canonical'",
+            ));
+        }
+
+        for user in &mut data.users {
+            if user.id == by_id {
+                user.username = username.to_string();
+                user.username_canonical = username_canonical.clone();
+            }
+        }
+        Ok(())
+    }
+
+    fn set_user_email(&self, by_id: i32, email: &str) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        let email_canonical = canonical(email);
+        if data
+            .users
+            .iter()
+            .any(|existing| existing.email_canonical == email_canonical && existing.id != by_id)
+        {
+            return Err(Self::unique_violation(
+                "Duplicate entry for key 'users_email_canonical'",
+            ));
+        }
+
+        for user in &mut data.users {
+            if user.id == by_id {
+                user.email = email.to_string();
+                user.email_canonical = email_canonical.clone();
+            }
+        }
+        Ok(())
+    }
+
+    fn set_user_totp_secret(&self, by_id: i32, secret: Option<&[u8]>) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        for user in &mut data.users {
+            if user.id == by_id {
+                user.totp_secret = secret.map(<[u8]>::to_vec);
+                user.totp_enabled = false;
+                user.totp_last_step = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_user_totp_enabled(&self, by_id: i32) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        for user in &mut data.users {
+            if user.id == by_id {
+                user.totp_enabled = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn advance_user_totp_step(&self, by_id: i32, step: i64) -> Result<bool, Error> {
+        let mut data = self.data.lock().unwrap();
+        let Some(user) = data.users.iter_mut().find(|user| user.id == by_id) else {
+            return Ok(false);
+        };
+        if matches!(user.totp_last_step, Some(last_step) if last_step >= step) {
+            return Ok(false);
+        }
+        user.totp_last_step = Some(step);
+        Ok(true)
+    }
+
+    fn renew_session(
+        &self,
+        by_digest: Vec<u8>,
+        last_seen: NaiveDateTime,
+        expires_on: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        for session in &mut data.sessions {
+            if session.key_digest == by_digest {
+                session.last_seen = last_seen;
+                session.expires_on = expires_on;
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_user(&self, by_id: i32, keep_posts: bool) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.sessions.retain(|session| session.user_id != by_id);
+        data.email_verifications
+            .retain(|verification| verification.user_id != by_id);
+        data.password_resets.retain(|reset| reset.user_id != by_id);
+        data.lockouts
+            .retain(|lockout| lockout.user_id != Some(by_id));
+        data.login_challenges
+            .retain(|challenge| challenge.user_id != by_id);
+        data.recovery_codes.retain(|code| code.user_id != by_id);
+
+        if keep_posts {
+            for post in &mut data.posts {
+                if post.author == Some(by_id) {
+                    post.author = None;
+                }
+            }
+        } else {
+            data.posts.retain(|post| post.author != Some(by_id));
+            data.remove_orphaned_revisions();
+        }
+
+        data.users.retain(|user| user.id != by_id);
+        Ok(())
+    }
+
+    fn remove_post(&self, by_id: i32) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.posts.retain(|post| post.id != by_id);
+        data.remove_orphaned_revisions();
+        Ok(())
+    }
+
+    fn remove_posts_deleted_before(&self, before: NaiveDateTime) -> Result<usize, Error> {
+        let mut data = self.data.lock().unwrap();
+        let count = data.posts.len();
+        data.posts
+            .retain(|post| !matches!(post.deleted_on, Some(deleted_on) if deleted_on <= before));
+        data.remove_orphaned_revisions();
+        Ok(count - data.posts.len())
+    }
+
+    fn remove_session_by_key(&self, by_digest: Vec<u8>) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.sessions
+            .retain(|session| session.key_digest != by_digest);
+        Ok(())
+    }
+
+    fn remove_user_session(&self, by_user_id: i32, by_id: i32) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.sessions
+            .retain(|session| session.user_id != by_user_id || session.id != by_id);
+        Ok(())
+    }
+
+    fn remove_user_sessions_except(&self, by_user_id: i32, except_id: i32) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.sessions
+            .retain(|session| session.user_id != by_user_id || session.id == except_id);
+        Ok(())
+    }
+
+    fn remove_user_sessions(&self, by_user_id: i32) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.sessions
+            .retain(|session| session.user_id != by_user_id);
+        Ok(())
+    }
+
+    fn remove_expired_sessions(&self, now: NaiveDateTime) -> Result<usize, Error> {
+        let mut data = self.data.lock().unwrap();
+        let count = data.sessions.len();
+        data.sessions.retain(|session| session.expires_on > now);
+        Ok(count - data.sessions.len())
+    }
+
+    fn remove_email_verifications_by_user_id(&self, by_user_id: i32) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.email_verifications
+            .retain(|verification| verification.user_id != by_user_id);
+        Ok(())
+    }
+
+    fn remove_expired_email_verifications(&self, now: NaiveDateTime) -> Result<usize, Error> {
+        let mut data = self.data.lock().unwrap();
+        let count = data.email_verifications.len();
+        data.email_verifications
+            .retain(|verification| verification.expires_on > now);
+        Ok(count - data.email_verifications.len())
+    }
+
+    fn remove_password_resets_by_user_id(&self, by_user_id: i32) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.password_resets
+            .retain(|reset| reset.user_id != by_user_id);
+        Ok(())
+    }
+
+    fn remove_expired_password_resets(&self, now: NaiveDateTime) -> Result<usize, Error> {
+        let mut data = self.data.lock().unwrap();
+        let count = data.password_resets.len();
+        data.password_resets.retain(|reset| reset.expires_on > now);
+        Ok(count - data.password_resets.len())
+    }
+
+    fn remove_login_challenge_by_token(&self, by_digest: Vec<u8>) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.login_challenges
+            .retain(|challenge| challenge.token_digest != by_digest);
+        Ok(())
+    }
+
+    fn remove_expired_login_challenges(&self, now: NaiveDateTime) -> Result<usize, Error> {
+        let mut data = self.data.lock().unwrap();
+        let count = data.login_challenges.len();
+        data.login_challenges
+            .retain(|challenge| challenge.expires_on > now);
+        Ok(count - data.login_challenges.len())
+    }
+
+    fn remove_recovery_code(&self, by_user_id: i32, by_digest: Vec<u8>) -> Result<bool, Error> {
+        let mut data = self.data.lock().unwrap();
+        let count = data.recovery_codes.len();
+        data.recovery_codes
+            .retain(|code| code.user_id != by_user_id || code.code_digest != by_digest);
+        Ok(data.recovery_codes.len() < count)
+    }
+
+    fn remove_recovery_codes_by_user_id(&self, by_user_id: i32) -> Result<(), Error> {
+        let mut data = self.data.lock().unwrap();
+        data.recovery_codes
+            .retain(|code| code.user_id != by_user_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+mod memory;
+mod sql;
+
+use std::{env, str::FromStr, time::Duration};
+
+use chrono::NaiveDateTime;
+
+pub use memory::MemoryDatabase;
+#[cfg(all(test, feature = "sqlite"))]
+pub use sql::SqliteDatabase;
+
+use crate::{
+    models::{
+        EmailVerification, LockoutWithUsername, LoginChallenge, MonthPostCount,
+        NewEmailVerification, NewLockout, NewLoginChallenge, NewPasswordReset, NewPost,
+        NewRecoveryCode, NewSession, NewUser, PasswordReset, Post, PostCursor, PostEdit,
+        PostRevision, PostSearch, PostWithAuthor, Session, User,
+    },
+    Error,
+};
+
+#[cfg(not(any(feature = "mysql", feature = "postgres", feature = "sqlite")))]
+compile_error!("at least one of the mysql, postgres or sqlite features has to be enabled");
+
+const DEFAULT_POOL_SIZE: u32 = 10;
+const DEFAULT_POOL_TIMEOUT_SECONDS: u64 = 5;
+
+pub trait Database: Send + Sync {
+    fn has_pending_migrations(&self) -> Result<bool, Error>;
+    fn run_pending_migrations(&self) -> Result<(), Error>;
+
+    fn get_user_by_id(&self, by_id: i32) -> Result<Option<User>, Error>;
+    fn get_user_by_username(&self, by_username: &str) -> Result<Option<User>, Error>;
+    fn get_user_by_email(&self, by_email: &str) -> Result<Option<User>, Error>;
+    // sessions are looked up by the digest of their key, see token_digest
+    fn get_session_by_key(&self, by_digest: Vec<u8>) -> Result<Option<Session>, Error>;
+    fn get_sessions_by_user_id(&self, by_user_id: i32) -> Result<Vec<Session>, Error>;
+    fn get_post_by_id(&self, by_id: i32) -> Result<Option<Post>, Error>;
+    // Listing posts joins in their authors right away, see PostWithAuthor.
+    fn get_post_with_author_by_id(&self, by_id: i32) -> Result<Option<PostWithAuthor>, Error>;
+    // The posts right before or after the cursor, newest first either way.
+    // Without a cursor, the latest ones. Neither includes posts in the trash.
+    fn get_posts_before(
+        &self,
+        before: Option<PostCursor>,
+        count: i64,
+    ) -> Result<Vec<PostWithAuthor>, Error>;
+    fn get_posts_after(&self, after: PostCursor, count: i64) -> Result<Vec<PostWithAuthor>, Error>;
+    // from is inclusive, to isn't, newest first and without posts in the trash
+    fn get_posts_created_between(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Vec<PostWithAuthor>, Error>;
+    // Posts matching the search, paged through like get_posts_before. MySQL
+    // uses its FULLTEXT index, which matches the start of whole words, the
+    // other backends match the words anywhere with LIKE.
+    fn search_posts(
+        &self,
+        search: &PostSearch,
+        before: Option<PostCursor>,
+        count: i64,
+    ) -> Result<Vec<PostWithAuthor>, Error>;
+    // only months with posts that aren't in the trash, oldest first
+    fn get_post_counts_by_month(&self) -> Result<Vec<MonthPostCount>, Error>;
+    // when the newest post that isn't in the trash and is older than the given
+    // time was created, for finding the previous month with posts
+    fn get_latest_post_time_before(
+        &self,
+        before: NaiveDateTime,
+    ) -> Result<Option<NaiveDateTime>, Error>;
+    // and the same for the next month, the given time is inclusive
+    fn get_earliest_post_time_from(
+        &self,
+        from: NaiveDateTime,
+    ) -> Result<Option<NaiveDateTime>, Error>;
+    fn get_posts_by_author(&self, by_author: i32) -> Result<Vec<Post>, Error>;
+    // the author's trash, most recently deleted first
+    fn get_deleted_posts_by_author(&self, by_author: i32) -> Result<Vec<Post>, Error>;
+    // earlier versions of a post, oldest first
+    fn get_post_revisions(&self, by_post_id: i32) -> Result<Vec<PostRevision>, Error>;
+    // of all of the author's posts at once, by post and then oldest first
+    fn get_post_revisions_by_author(&self, by_author: i32) -> Result<Vec<PostRevision>, Error>;
+    // newest first, with the username of the locked out account if there is one
+    fn get_latest_x_lockouts(&self, count: i64) -> Result<Vec<LockoutWithUsername>, Error>;
+    fn get_email_verification_by_token(
+        &self,
+        by_digest: Vec<u8>,
+    ) -> Result<Option<EmailVerification>, Error>;
+    // the one that expires last, i.e. the one sent last
+    fn get_latest_email_verification_by_user_id(
+        &self,
+        by_user_id: i32,
+    ) -> Result<Option<EmailVerification>, Error>;
+    fn get_password_reset_by_token(
+        &self,
+        by_digest: Vec<u8>,
+    ) -> Result<Option<PasswordReset>, Error>;
+    fn get_login_challenge_by_token(
+        &self,
+        by_digest: Vec<u8>,
+    ) -> Result<Option<LoginChallenge>, Error>;
+    fn get_recovery_code_count(&self, by_user_id: i32) -> Result<i64, Error>;
+
+    fn create_user(&self, user: NewUser) -> Result<(), Error>;
+    fn create_session(&self, session: NewSession) -> Result<(), Error>;
+    fn create_post(&self, post: NewPost) -> Result<(), Error>;
+    fn create_lockout(&self, lockout: NewLockout) -> Result<(), Error>;
+    fn create_email_verification(&self, verification: NewEmailVerification) -> Result<(), Error>;
+    fn create_password_reset(&self, reset: NewPasswordReset) -> Result<(), Error>;
+    fn create_login_challenge(&self, challenge: NewLoginChallenge) -> Result<(), Error>;
+    fn create_recovery_codes(&self, codes: Vec<NewRecoveryCode>) -> Result<(), Error>;
+
+    // keeps the version being replaced as a revision
+    fn edit_post(&self, by_id: i32, edit: PostEdit) -> Result<(), Error>;
+    // moves the post to the trash, or back out of it with None
+    fn set_post_deleted_on(
+        &self,
+        by_id: i32,
+        deleted_on: Option<NaiveDateTime>,
+    ) -> Result<(), Error>;
+
+    // only the tests make admins, see the README for how it is done otherwise
+    #[cfg(test)]
+    fn set_user_admin(&self, by_id: i32, is_admin: bool) -> Result<(), Error>;
+    fn set_user_email_verified(&self, by_id: i32) -> Result<(), Error>;
+    // expects an already hashed password
+    fn set_user_password(&self, by_id: i32, password: &str) -> Result<(), Error>;
+    fn set_user_username(&self, by_id: i32, username: &str) -> Result<(), Error>;
+    fn set_user_email(&self, by_id: i32, email: &str) -> Result<(), Error>;
+    // also turns two-factor authentication off until set_user_totp_enabled
+    fn set_user_totp_secret(&self, by_id: i32, secret: Option<&[u8]>) -> Result<(), Error>;
+    fn set_user_totp_enabled(&self, by_id: i32) -> Result<(), Error>;
+    // false when the step isn't past the last one used, so that each code
+    // works only once even with concurrent requests
+    fn advance_user_totp_step(&self, by_id: i32, step: i64) -> Result<bool, Error>;
+
+    fn renew_session(
+        &self,
+        by_digest: Vec<u8>,
+        last_seen: NaiveDateTime,
+        expires_on: NaiveDateTime,
+    ) -> Result<(), Error>;
+
+    // removes the user along with everything that refers to them, kept posts
+    // are left without an author
+    fn remove_user(&self, by_id: i32, keep_posts: bool) -> Result<(), Error>;
+    // both take the revisions of the posts with them
+    fn remove_post(&self, by_id: i32) -> Result<(), Error>;
+    fn remove_posts_deleted_before(&self, before: NaiveDateTime) -> Result<usize, Error>;
+    fn remove_session_by_key(&self, by_digest: Vec<u8>) -> Result<(), Error>;
+    // both only ever touch sessions of the given user
+    fn remove_user_session(&self, by_user_id: i32, by_id: i32) -> Result<(), Error>;
+    fn remove_user_sessions_except(&self, by_user_id: i32, except_id: i32) -> Result<(), Error>;
+    fn remove_user_sessions(&self, by_user_id: i32) -> Result<(), Error>;
+    fn remove_expired_sessions(&self, now: NaiveDateTime) -> Result<usize, Error>;
+    fn remove_email_verifications_by_user_id(&self, by_user_id: i32) -> Result<(), Error>;
+    fn remove_expired_email_verifications(&self, now: NaiveDateTime) -> Result<usize, Error>;
+    fn remove_password_resets_by_user_id(&self, by_user_id: i32) -> Result<(), Error>;
+    fn remove_expired_password_resets(&self, now: NaiveDateTime) -> Result<usize, Error>;
+    fn remove_login_challenge_by_token(&self, by_digest: Vec<u8>) -> Result<(), Error>;
+    fn remove_expired_login_challenges(&self, now: NaiveDateTime) -> Result<usize, Error>;
+    // false when the user has no such code, otherwise it can't be used again
+    fn remove_recovery_code(&self, by_user_id: i32, by_digest: Vec<u8>) -> Result<bool, Error>;
+    fn remove_recovery_codes_by_user_id(&self, by_user_id: i32) -> Result<(), Error>;
+}
+
+pub struct PoolSettings {
+    pub size: u32,
+    pub timeout: Duration,
+}
+
+impl PoolSettings {
+    fn from_env() -> Result<PoolSettings, Error> {
+        Ok(PoolSettings {
+            size: env_var_or("DATABASE_POOL_SIZE", DEFAULT_POOL_SIZE)?,
+            timeout: Duration::from_secs(env_var_or(
+                "DATABASE_POOL_TIMEOUT",
+                DEFAULT_POOL_TIMEOUT_SECONDS,
+            )?),
+        })
+    }
+}
+
+fn env_var_or<T: FromStr>(name: &'static str, default: T) -> Result<T, Error> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| Error::InvalidDatabaseConfiguration(name)),
+        Err(_) => Ok(default),
+    }
+}
+
+// picks the backend based on the scheme of DATABASE_URL, out of the ones
+// enabled through cargo features
+pub fn connect() -> Result<Box<dyn Database>, Error> {
+    let database_url = env::var("DATABASE_URL").map_err(|_| Error::MissingDatabaseUrl)?;
+    let pool_settings = PoolSettings::from_env()?;
+
+    // like diesel_cli, anything without a scheme is taken to be a SQLite file
+    let scheme = database_url
+        .split_once("://")
+        .map_or("sqlite", |(scheme, _)| scheme);
+
+    match scheme {
+        #[cfg(feature = "mysql")]
+        "mysql" => Ok(Box::new(sql::MysqlDatabase::new(
+            &database_url,
+            &pool_settings,
+        )?)),
+        #[cfg(feature = "postgres")]
+        "postgres" | "postgresql" => Ok(Box::new(sql::PostgresDatabase::new(
+            &database_url,
+            &pool_settings,
+        )?)),
+        // diesel wants a plain path for SQLite, the scheme is optional
+        #[cfg(feature = "sqlite")]
+        "sqlite" => Ok(Box::new(sql::SqliteDatabase::new(
+            database_url.trim_start_matches("sqlite://"),
+            &pool_settings,
+        )?)),
+        "memory" => Ok(Box::new(MemoryDatabase::new())),
+        _ => Err(Error::UnsupportedDatabaseUrl),
+    }
+}
+
+// Brings the schema up to date with the migrations embedded in the binary.
+// With DATABASE_AUTO_MIGRATE=false, pending migrations are only reported and
+// have to be applied by hand (e.g. with diesel_cli).
+pub fn migrate(database: &dyn Database) -> Result<(), Error> {
+    if env_var_or("DATABASE_AUTO_MIGRATE", true)? {
+        database.run_pending_migrations()
+    } else if database.has_pending_migrations()? {
+        Err(Error::PendingMigrations)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,1101 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDateTime;
+use diesel::{
+    dsl::{max, min, sql},
+    prelude::*,
+    r2d2::{ConnectionManager, Pool, PooledConnection, R2D2Connection},
+    result::Error as DieselError,
+    sql_types::{Bool, Text},
+};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+use super::PoolSettings;
+use crate::{
+    models::{
+        canonical, EmailVerification, Lockout, LockoutWithUsername, LoginChallenge, MonthPostCount,
+        NewEmailVerification, NewLockout, NewLoginChallenge, NewPasswordReset, NewPost,
+        NewPostRevision, NewRecoveryCode, NewSession, NewUser, PasswordReset, Post, PostCursor,
+        PostEdit, PostRevision, PostSearch, PostWithAuthor, Session, User,
+    },
+    schema::{
+        email_verifications, lockouts, login_challenges, password_resets, post_revisions, posts,
+        recovery_codes, sessions, users,
+    },
+    Error,
+};
+
+#[cfg(feature = "mysql")]
+pub type MysqlDatabase = SqlDatabase<diesel::mysql::MysqlConnection>;
+#[cfg(feature = "postgres")]
+pub type PostgresDatabase = SqlDatabase<diesel::pg::PgConnection>;
+#[cfg(feature = "sqlite")]
+pub type SqliteDatabase = SqlDatabase<diesel::sqlite::SqliteConnection>;
+
+// for searching regardless of case without depending on the collation
+define_sql_function!(fn lower(x: Text) -> Text);
+
+// The migration that makes the canonical usernames and e-mail addresses
+// unique. They are filled in by the application right before it, see
+// fill_in_canonical_names.
+const UNIQUE_CANONICAL_NAMES_VERSION: &str = "20230302105319";
+
+// Accounts that can't be told apart anymore once their usernames or e-mail
+// addresses are compared in canonical form, described for whoever runs the
+// migrations.
+fn canonical_clashes(accounts: &[(i32, String, String)]) -> Vec<String> {
+    let mut usernames = HashMap::new();
+    let mut emails = HashMap::new();
+    let mut clashes = Vec::new();
+
+    for (id, username, email) in accounts {
+        if let Some(other_id) = usernames.insert(canonical(username), id) {
+            clashes.push(format!("users {other_id} and {id} have the same username"));
+        }
+        if let Some(other_id) = emails.insert(canonical(email), id) {
+            clashes.push(format!(
+                "users {other_id} and {id} have the same e-mail address"
+            ));
+        }
+    }
+    clashes
+}
+
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+// Keeps track of a lost database connection. While the delay is running,
+// requests fail immediately instead of each waiting out the pool timeout;
+// every failed reconnection attempt doubles the delay.
+struct ReconnectBackoff {
+    retry_at: Option<Instant>,
+    delay: Duration,
+}
+
+impl ReconnectBackoff {
+    fn new() -> Self {
+        Self {
+            retry_at: None,
+            delay: INITIAL_RECONNECT_DELAY,
+        }
+    }
+
+    fn is_waiting(&self) -> bool {
+        self.retry_at
+            .map_or(false, |retry_at| Instant::now() < retry_at)
+    }
+
+    fn failed(&mut self) {
+        if self.retry_at.is_some() {
+            self.delay = (self.delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+        self.retry_at = Some(Instant::now() + self.delay);
+    }
+
+    fn succeeded(&mut self) {
+        self.retry_at = None;
+        self.delay = INITIAL_RECONNECT_DELAY;
+    }
+}
+
+// A Database implementation over any of the diesel backends, the queries
+// themselves are written once in `impl_database!` below.
+pub struct SqlDatabase<C: R2D2Connection + Send + 'static> {
+    pool: Pool<ConnectionManager<C>>,
+    backoff: Mutex<ReconnectBackoff>,
+}
+
+impl<C: R2D2Connection + Send + 'static> SqlDatabase<C> {
+    pub fn new(database_url: &str, pool_settings: &PoolSettings) -> Result<Self, Error> {
+        // connections are pinged on checkout, so ones broken by a database
+        // restart get replaced with fresh ones by the pool itself
+        let pool = Pool::builder()
+            .max_size(pool_settings.size)
+            .connection_timeout(pool_settings.timeout)
+            .test_on_check_out(true)
+            .build(ConnectionManager::new(database_url))
+            .map_err(|_| Error::UnableToConnectToDatabase)?;
+
+        Ok(Self {
+            pool,
+            backoff: Mutex::new(ReconnectBackoff::new()),
+        })
+    }
+
+    // waits up to the configured checkout timeout for a free connection
+    fn connection(&self) -> Result<PooledConnection<ConnectionManager<C>>, Error> {
+        if self.backoff.lock().unwrap().is_waiting() {
+            return Err(Error::DatabaseUnavailable);
+        }
+
+        if let Ok(connection) = self.pool.get() {
+            self.backoff.lock().unwrap().succeeded();
+            Ok(connection)
+        } else {
+            self.backoff.lock().unwrap().failed();
+            Err(Error::DatabaseUnavailable)
+        }
+    }
+
+    fn run<T>(&self, query: impl FnOnce(&mut C) -> Result<T, DieselError>) -> Result<T, Error> {
+        let mut connection = self.connection()?;
+        query(&mut connection).map_err(|error| match error {
+            // a query can also fail because the server went away after the
+            // connection was checked out, which the pool has no way of knowing
+            DieselError::DatabaseError(..) if connection.ping().is_err() => {
+                self.backoff.lock().unwrap().failed();
+                Error::DatabaseUnavailable
+            }
+            error => Error::Diesel(error),
+        })
+    }
+}
+
+// The query builder is generic over the backend, but spelling out the trait
+// bounds for every query by hand isn't worth it, so the implementation is
+// instantiated once per connection type instead. Only MySQL has FULLTEXT
+// indexes and MATCH ... AGAINST, the others fall back to LIKE when searching.
+// Each backend also has its own way of getting the year and month out of
+// posts.created_on, which the archive groups posts by.
+macro_rules! impl_database {
+    (
+        $connection:ty,
+        $migrations:literal,
+        fulltext_search: $fulltext_search:literal,
+        year: $year:literal,
+        month: $month:literal
+    ) => {
+        impl SqlDatabase<$connection> {
+            // SQL can't normalize Unicode, so the canonical forms of existing
+            // accounts are computed here. Accounts that would clash are
+            // reported instead, one of each has to be renamed by hand first.
+            fn fill_in_canonical_names(connection: &mut $connection) -> Result<(), Error> {
+                let accounts = users::table
+                    .select((users::id, users::username, users::email))
+                    .load::<(i32, String, String)>(connection)?;
+
+                let clashes = canonical_clashes(&accounts);
+                if !clashes.is_empty() {
+                    return Err(Error::Migration(format!(
+                        "usernames and e-mail addresses have to be unique regardless of case \
+                        and Unicode form, but {}",
+                        clashes.join(", ")
+                    )));
+                }
+
+                connection.transaction::<_, DieselError, _>(|connection| {
+                    for (id, username, email) in &accounts {
+                        diesel::update(users::table.filter(users::id.eq(id)))
+                            .set((
+                                users::username_canonical.eq(canonical(username)),
+                                users::email_canonical.eq(canonical(email)),
+                            ))
+                            .execute(connection)?;
+                    }
+                    Ok(())
+                })?;
+                Ok(())
+            }
+        }
+
+        impl crate::database::Database for SqlDatabase<$connection> {
+            fn has_pending_migrations(&self) -> Result<bool, Error> {
+                const MIGRATIONS: EmbeddedMigrations = embed_migrations!($migrations);
+                self.connection()?
+                    .has_pending_migration(MIGRATIONS)
+                    .map_err(|error| Error::Migration(error.to_string()))
+            }
+
+            // one at a time, so that the application can step in between
+            fn run_pending_migrations(&self) -> Result<(), Error> {
+                const MIGRATIONS: EmbeddedMigrations = embed_migrations!($migrations);
+                let mut connection = self.connection()?;
+                let pending = connection
+                    .pending_migrations(MIGRATIONS)
+                    .map_err(|error| Error::Migration(error.to_string()))?;
+
+                for migration in pending {
+                    if migration.name().version().to_string() == UNIQUE_CANONICAL_NAMES_VERSION {
+                        Self::fill_in_canonical_names(&mut connection)?;
+                    }
+                    connection
+                        .run_migration(&migration)
+                        .map_err(|error| Error::Migration(error.to_string()))?;
+                }
+                Ok(())
+            }
+
+            fn get_user_by_id(&self, by_id: i32) -> Result<Option<User>, Error> {
+                use crate::schema::users::dsl::{id, users};
+                self.run(|connection| {
+                    users
+                        .filter(id.eq(by_id))
+                        .limit(1)
+                        .first::<User>(connection)
+                        .optional()
+                })
+            }
+
+            fn get_user_by_username(&self, by_username: &str) -> Result<Option<User>, Error> {
+                use crate::schema::users::dsl::{username_canonical, users};
+                self.run(|connection| {
+                    users
+                        .filter(username_canonical.eq(canonical(by_username)))
+                        .limit(1)
+                        .first::<User>(connection)
+                        .optional()
+                })
+            }
+
+            fn get_user_by_email(&self, by_email: &str) -> Result<Option<User>, Error> {
+                use crate::schema::users::dsl::{email_canonical, users};
+                self.run(|connection| {
+                    users
+                        .filter(email_canonical.eq(canonical(by_email)))
+                        .limit(1)
+                        .first::<User>(connection)
+                        .optional()
+                })
+            }
+
+            fn get_session_by_key(&self, by_digest: Vec<u8>) -> Result<Option<Session>, Error> {
+                use crate::schema::sessions::dsl::{key_digest, sessions};
+                self.run(|connection| {
+                    sessions
+                        .filter(key_digest.eq(by_digest))
+                        .limit(1)
+                        .first::<Session>(connection)
+                        .optional()
+                })
+            }
+
+            fn get_sessions_by_user_id(&self, by_user_id: i32) -> Result<Vec<Session>, Error> {
+                use crate::schema::sessions::dsl::{last_seen, sessions, user_id};
+                self.run(|connection| {
+                    sessions
+                        .filter(user_id.eq(by_user_id))
+                        .order(last_seen.desc())
+                        .load::<Session>(connection)
+                })
+            }
+
+            fn get_post_by_id(&self, by_id: i32) -> Result<Option<Post>, Error> {
+                use crate::schema::posts::dsl::{id, posts};
+                self.run(|connection| {
+                    posts
+                        .filter(id.eq(by_id))
+                        .limit(1)
+                        .first::<Post>(connection)
+                        .optional()
+                })
+            }
+
+            fn get_post_with_author_by_id(
+                &self,
+                by_id: i32,
+            ) -> Result<Option<PostWithAuthor>, Error> {
+                let row = self.run(|connection| {
+                    posts::table
+                        .left_join(users::table)
+                        .select((posts::all_columns, users::username.nullable()))
+                        .filter(posts::id.eq(by_id))
+                        .first::<(Post, Option<String>)>(connection)
+                        .optional()
+                })?;
+                Ok(row.map(PostWithAuthor::from))
+            }
+
+            fn get_posts_before(
+                &self,
+                before: Option<PostCursor>,
+                count: i64,
+            ) -> Result<Vec<PostWithAuthor>, Error> {
+                let rows = self.run(|connection| {
+                    let mut query = posts::table
+                        .left_join(users::table)
+                        .select((posts::all_columns, users::username.nullable()))
+                        .filter(posts::deleted_on.is_null())
+                        .order((posts::created_on.desc(), posts::id.desc()))
+                        .limit(count)
+                        .into_boxed();
+                    if let Some(before) = before {
+                        query = query.filter(
+                            posts::created_on.lt(before.created_on).or(posts::created_on
+                                .eq(before.created_on)
+                                .and(posts::id.lt(before.id))),
+                        );
+                    }
+                    query.load::<(Post, Option<String>)>(connection)
+                })?;
+                Ok(rows.into_iter().map(PostWithAuthor::from).collect())
+            }
+
+            fn get_posts_after(
+                &self,
+                after: PostCursor,
+                count: i64,
+            ) -> Result<Vec<PostWithAuthor>, Error> {
+                // the ones closest to the cursor are the oldest of the newer posts
+                let rows = self.run(|connection| {
+                    posts::table
+                        .left_join(users::table)
+                        .select((posts::all_columns, users::username.nullable()))
+                        .filter(posts::deleted_on.is_null())
+                        .filter(
+                            posts::created_on.gt(after.created_on).or(posts::created_on
+                                .eq(after.created_on)
+                                .and(posts::id.gt(after.id))),
+                        )
+                        .order((posts::created_on.asc(), posts::id.asc()))
+                        .limit(count)
+                        .load::<(Post, Option<String>)>(connection)
+                })?;
+                Ok(rows.into_iter().rev().map(PostWithAuthor::from).collect())
+            }
+
+            fn get_posts_created_between(
+                &self,
+                from: NaiveDateTime,
+                to: NaiveDateTime,
+            ) -> Result<Vec<PostWithAuthor>, Error> {
+                let rows = self.run(|connection| {
+                    posts::table
+                        .left_join(users::table)
+                        .select((posts::all_columns, users::username.nullable()))
+                        .filter(posts::deleted_on.is_null())
+                        .filter(posts::created_on.ge(from))
+                        .filter(posts::created_on.lt(to))
+                        .order((posts::created_on.desc(), posts::id.desc()))
+                        .load::<(Post, Option<String>)>(connection)
+                })?;
+                Ok(rows.into_iter().map(PostWithAuthor::from).collect())
+            }
+
+            fn search_posts(
+                &self,
+                search: &PostSearch,
+                before: Option<PostCursor>,
+                count: i64,
+            ) -> Result<Vec<PostWithAuthor>, Error> {
+                const FULLTEXT_SEARCH: bool = $fulltext_search;
+                let rows = self.run(|connection| {
+                    let mut query = posts::table
+                        .left_join(users::table)
+                        .select((posts::all_columns, users::username.nullable()))
+                        .filter(posts::deleted_on.is_null())
+                        .order((posts::created_on.desc(), posts::id.desc()))
+                        .limit(count)
+                        .into_boxed();
+                    if let Some(before) = before {
+                        query = query.filter(
+                            posts::created_on.lt(before.created_on).or(posts::created_on
+                                .eq(before.created_on)
+                                .and(posts::id.lt(before.id))),
+                        );
+                    }
+                    if let Some(author) = search.author {
+                        query = query.filter(posts::author.eq(author));
+                    }
+                    if let Some(from) = search.from {
+                        query = query.filter(posts::created_on.ge(from));
+                    }
+                    if let Some(to) = search.to {
+                        query = query.filter(posts::created_on.lt(to));
+                    }
+
+                    if FULLTEXT_SEARCH && !search.words.is_empty() {
+                        // every word is required, and may be the start of a longer one
+                        let against = search
+                            .words
+                            .iter()
+                            .map(|word| format!("+{word}*"))
+                            .collect::<Vec<String>>()
+                            .join(" ");
+                        query = query.filter(
+                            sql::<Bool>(
+                                "MATCH (posts.title, posts.description, posts.content) AGAINST (",
+                            )
+                            .bind::<Text, _>(against)
+                            .sql(" IN BOOLEAN MODE)"),
+                        );
+                    } else {
+                        // words are letters and digits only, so there are no
+                        // wildcards in them to escape
+                        for word in &search.words {
+                            let pattern = format!("%{word}%");
+                            query = query.filter(
+                                lower(posts::title)
+                                    .like(pattern.clone())
+                                    .or(lower(posts::description).like(pattern.clone()))
+                                    .or(lower(posts::content).like(pattern)),
+                            );
+                        }
+                    }
+
+                    query.load::<(Post, Option<String>)>(connection)
+                })?;
+                Ok(rows.into_iter().map(PostWithAuthor::from).collect())
+            }
+
+            fn get_post_counts_by_month(&self) -> Result<Vec<MonthPostCount>, Error> {
+                self.run(|connection| {
+                    diesel::sql_query(concat!(
+                        "SELECT ",
+                        $year,
+                        " AS year, ",
+                        $month,
+                        " AS month, COUNT(*) AS count FROM posts WHERE deleted_on IS NULL ",
+                        "GROUP BY year, month ORDER BY year, month",
+                    ))
+                    .load::<MonthPostCount>(connection)
+                })
+            }
+
+            fn get_latest_post_time_before(
+                &self,
+                before: NaiveDateTime,
+            ) -> Result<Option<NaiveDateTime>, Error> {
+                use crate::schema::posts::dsl::{created_on, deleted_on, posts};
+                self.run(|connection| {
+                    posts
+                        .filter(deleted_on.is_null())
+                        .filter(created_on.lt(before))
+                        .select(max(created_on))
+                        .first::<Option<NaiveDateTime>>(connection)
+                })
+            }
+
+            fn get_earliest_post_time_from(
+                &self,
+                from: NaiveDateTime,
+            ) -> Result<Option<NaiveDateTime>, Error> {
+                use crate::schema::posts::dsl::{created_on, deleted_on, posts};
+                self.run(|connection| {
+                    posts
+                        .filter(deleted_on.is_null())
+                        .filter(created_on.ge(from))
+                        .select(min(created_on))
+                        .first::<Option<NaiveDateTime>>(connection)
+                })
+            }
+
+            fn get_posts_by_author(&self, by_author: i32) -> Result<Vec<Post>, Error> {
+                use crate::schema::posts::dsl::{author, created_on, posts};
+                self.run(|connection| {
+                    posts
+                        .filter(author.eq(by_author))
+                        .order(created_on.desc())
+                        .load::<Post>(connection)
+                })
+            }
+
+            fn get_deleted_posts_by_author(&self, by_author: i32) -> Result<Vec<Post>, Error> {
+                use crate::schema::posts::dsl::{author, deleted_on, posts};
+                self.run(|connection| {
+                    posts
+                        .filter(author.eq(by_author))
+                        .filter(deleted_on.is_not_null())
+                        .order(deleted_on.desc())
+                        .load::<Post>(connection)
+                })
+            }
+
+            fn get_post_revisions(&self, by_post_id: i32) -> Result<Vec<PostRevision>, Error> {
+                use crate::schema::post_revisions::dsl::{id, post_id, post_revisions};
+                self.run(|connection| {
+                    post_revisions
+                        .filter(post_id.eq(by_post_id))
+                        .order(id.asc())
+                        .load::<PostRevision>(connection)
+                })
+            }
+
+            fn get_post_revisions_by_author(
+                &self,
+                by_author: i32,
+            ) -> Result<Vec<PostRevision>, Error> {
+                self.run(|connection| {
+                    post_revisions::table
+                        .inner_join(posts::table)
+                        .filter(posts::author.eq(by_author))
+                        .select(post_revisions::all_columns)
+                        .order((post_revisions::post_id.asc(), post_revisions::id.asc()))
+                        .load::<PostRevision>(connection)
+                })
+            }
+
+            fn get_latest_x_lockouts(&self, count: i64) -> Result<Vec<LockoutWithUsername>, Error> {
+                let rows = self.run(|connection| {
+                    lockouts::table
+                        .left_join(users::table)
+                        .select((lockouts::all_columns, users::username.nullable()))
+                        .order(lockouts::locked_on.desc())
+                        .limit(count)
+                        .load::<(Lockout, Option<String>)>(connection)
+                })?;
+                Ok(rows.into_iter().map(LockoutWithUsername::from).collect())
+            }
+
+            fn get_email_verification_by_token(
+                &self,
+                by_digest: Vec<u8>,
+            ) -> Result<Option<EmailVerification>, Error> {
+                use crate::schema::email_verifications::dsl::{email_verifications, token_digest};
+                self.run(|connection| {
+                    email_verifications
+                        .filter(token_digest.eq(by_digest))
+                        .limit(1)
+                        .first::<EmailVerification>(connection)
+                        .optional()
+                })
+            }
+
+            fn get_latest_email_verification_by_user_id(
+                &self,
+                by_user_id: i32,
+            ) -> Result<Option<EmailVerification>, Error> {
+                use crate::schema::email_verifications::dsl::{
+                    email_verifications, expires_on, user_id,
+                };
+                self.run(|connection| {
+                    email_verifications
+                        .filter(user_id.eq(by_user_id))
+                        .order(expires_on.desc())
+                        .limit(1)
+                        .first::<EmailVerification>(connection)
+                        .optional()
+                })
+            }
+
+            fn get_password_reset_by_token(
+                &self,
+                by_digest: Vec<u8>,
+            ) -> Result<Option<PasswordReset>, Error> {
+                use crate::schema::password_resets::dsl::{password_resets, token_digest};
+                self.run(|connection| {
+                    password_resets
+                        .filter(token_digest.eq(by_digest))
+                        .limit(1)
+                        .first::<PasswordReset>(connection)
+                        .optional()
+                })
+            }
+
+            fn get_login_challenge_by_token(
+                &self,
+                by_digest: Vec<u8>,
+            ) -> Result<Option<LoginChallenge>, Error> {
+                use crate::schema::login_challenges::dsl::{login_challenges, token_digest};
+                self.run(|connection| {
+                    login_challenges
+                        .filter(token_digest.eq(by_digest))
+                        .limit(1)
+                        .first::<LoginChallenge>(connection)
+                        .optional()
+                })
+            }
+
+            fn get_recovery_code_count(&self, by_user_id: i32) -> Result<i64, Error> {
+                use crate::schema::recovery_codes::dsl::{recovery_codes, user_id};
+                self.run(|connection| {
+                    recovery_codes
+                        .filter(user_id.eq(by_user_id))
+                        .count()
+                        .get_result(connection)
+                })
+            }
+
+            fn create_user(&self, user: NewUser) -> Result<(), Error> {
+                self.run(|connection| {
+                    diesel::insert_into(users::table)
+                        .values(user)
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn create_session(&self, session: NewSession) -> Result<(), Error> {
+                self.run(|connection| {
+                    diesel::insert_into(sessions::table)
+                        .values(session)
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn create_post(&self, post: NewPost) -> Result<(), Error> {
+                self.run(|connection| {
+                    diesel::insert_into(posts::table)
+                        .values(post)
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn create_lockout(&self, lockout: NewLockout) -> Result<(), Error> {
+                self.run(|connection| {
+                    diesel::insert_into(lockouts::table)
+                        .values(lockout)
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn create_email_verification(
+                &self,
+                verification: NewEmailVerification,
+            ) -> Result<(), Error> {
+                self.run(|connection| {
+                    diesel::insert_into(email_verifications::table)
+                        .values(verification)
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn create_password_reset(&self, reset: NewPasswordReset) -> Result<(), Error> {
+                self.run(|connection| {
+                    diesel::insert_into(password_resets::table)
+                        .values(reset)
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn create_login_challenge(&self, challenge: NewLoginChallenge) -> Result<(), Error> {
+                self.run(|connection| {
+                    diesel::insert_into(login_challenges::table)
+                        .values(challenge)
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn create_recovery_codes(&self, codes: Vec<NewRecoveryCode>) -> Result<(), Error> {
+                self.run(|connection| {
+                    diesel::insert_into(recovery_codes::table)
+                        .values(codes)
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn edit_post(&self, by_id: i32, edit: PostEdit) -> Result<(), Error> {
+                self.run(|connection| {
+                    // the old version is either kept or the edit doesn't happen
+                    connection.transaction::<_, DieselError, _>(|connection| {
+                        let post = posts::table
+                            .filter(posts::id.eq(by_id))
+                            .first::<Post>(connection)?;
+                        diesel::insert_into(post_revisions::table)
+                            .values(NewPostRevision {
+                                post_id: post.id,
+                                created_on: post.last_changed_on(),
+                                title: &post.title,
+                                description: &post.description,
+                                content: &post.content,
+                            })
+                            .execute(connection)?;
+                        diesel::update(posts::table)
+                            .filter(posts::id.eq(by_id))
+                            .set(edit)
+                            .execute(connection)?;
+                        Ok(())
+                    })
+                })
+            }
+
+            fn set_post_deleted_on(
+                &self,
+                by_id: i32,
+                new_deleted_on: Option<NaiveDateTime>,
+            ) -> Result<(), Error> {
+                use crate::schema::posts::dsl::{deleted_on, id, posts};
+                self.run(|connection| {
+                    diesel::update(posts)
+                        .filter(id.eq(by_id))
+                        .set(deleted_on.eq(new_deleted_on))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            #[cfg(test)]
+            fn set_user_admin(&self, by_id: i32, new_is_admin: bool) -> Result<(), Error> {
+                use crate::schema::users::dsl::{id, is_admin, users};
+                self.run(|connection| {
+                    diesel::update(users)
+                        .filter(id.eq(by_id))
+                        .set(is_admin.eq(new_is_admin))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn set_user_email_verified(&self, by_id: i32) -> Result<(), Error> {
+                use crate::schema::users::dsl::{email_verified, id, users};
+                self.run(|connection| {
+                    diesel::update(users)
+                        .filter(id.eq(by_id))
+                        .set(email_verified.eq(true))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn set_user_password(&self, by_id: i32, new_password: &str) -> Result<(), Error> {
+                use crate::schema::users::dsl::{id, password, users};
+                self.run(|connection| {
+                    diesel::update(users)
+                        .filter(id.eq(by_id))
+                        .set(password.eq(new_password))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn set_user_username(&self, by_id: i32, new_username: &str) -> Result<(), Error> {
+                use crate::schema::users::dsl::{id, username, username_canonical, users};
+                self.run(|connection| {
+                    diesel::update(users)
+                        .filter(id.eq(by_id))
+                        .set((
+                            username.eq(new_username),
+                            username_canonical.eq(canonical(new_username)),
+                        ))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn set_user_email(&self, by_id: i32, new_email: &str) -> Result<(), Error> {
+                use crate::schema::users::dsl::{email, email_canonical, id, users};
+                self.run(|connection| {
+                    diesel::update(users)
+                        .filter(id.eq(by_id))
+                        .set((
+                            email.eq(new_email),
+                            email_canonical.eq(canonical(new_email)),
+                        ))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn set_user_totp_secret(
+                &self,
+                by_id: i32,
+                new_secret: Option<&[u8]>,
+            ) -> Result<(), Error> {
+                use crate::schema::users::dsl::{
+                    id, totp_enabled, totp_last_step, totp_secret, users,
+                };
+                self.run(|connection| {
+                    diesel::update(users)
+                        .filter(id.eq(by_id))
+                        .set((
+                            totp_secret.eq(new_secret),
+                            totp_enabled.eq(false),
+                            totp_last_step.eq(None::<i64>),
+                        ))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn set_user_totp_enabled(&self, by_id: i32) -> Result<(), Error> {
+                use crate::schema::users::dsl::{id, totp_enabled, users};
+                self.run(|connection| {
+                    diesel::update(users)
+                        .filter(id.eq(by_id))
+                        .set(totp_enabled.eq(true))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn advance_user_totp_step(&self, by_id: i32, step: i64) -> Result<bool, Error> {
+                use crate::schema::users::dsl::{id, totp_last_step, users};
+                let updated = self.run(|connection| {
+                    diesel::update(users)
+                        .filter(id.eq(by_id))
+                        .filter(totp_last_step.is_null().or(totp_last_step.lt(step)))
+                        .set(totp_last_step.eq(step))
+                        .execute(connection)
+                })?;
+                Ok(updated > 0)
+            }
+
+            fn renew_session(
+                &self,
+                by_digest: Vec<u8>,
+                new_last_seen: NaiveDateTime,
+                new_expires_on: NaiveDateTime,
+            ) -> Result<(), Error> {
+                use crate::schema::sessions::dsl::{expires_on, key_digest, last_seen, sessions};
+                self.run(|connection| {
+                    diesel::update(sessions)
+                        .filter(key_digest.eq(by_digest))
+                        .set((last_seen.eq(new_last_seen), expires_on.eq(new_expires_on)))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn remove_user(&self, by_id: i32, keep_posts: bool) -> Result<(), Error> {
+                self.run(|connection| {
+                    // a half deleted account is of no use to anyone
+                    connection.transaction::<_, DieselError, _>(|connection| {
+                        diesel::delete(sessions::table)
+                            .filter(sessions::user_id.eq(by_id))
+                            .execute(connection)?;
+                        diesel::delete(email_verifications::table)
+                            .filter(email_verifications::user_id.eq(by_id))
+                            .execute(connection)?;
+                        diesel::delete(password_resets::table)
+                            .filter(password_resets::user_id.eq(by_id))
+                            .execute(connection)?;
+                        diesel::delete(lockouts::table)
+                            .filter(lockouts::user_id.eq(by_id))
+                            .execute(connection)?;
+                        diesel::delete(login_challenges::table)
+                            .filter(login_challenges::user_id.eq(by_id))
+                            .execute(connection)?;
+                        diesel::delete(recovery_codes::table)
+                            .filter(recovery_codes::user_id.eq(by_id))
+                            .execute(connection)?;
+
+                        if keep_posts {
+                            diesel::update(posts::table)
+                                .filter(posts::author.eq(by_id))
+                                .set(posts::author.eq(None::<i32>))
+                                .execute(connection)?;
+                        } else {
+                            diesel::delete(post_revisions::table)
+                                .filter(
+                                    post_revisions::post_id.eq_any(
+                                        posts::table
+                                            .filter(posts::author.eq(by_id))
+                                            .select(posts::id),
+                                    ),
+                                )
+                                .execute(connection)?;
+                            diesel::delete(posts::table)
+                                .filter(posts::author.eq(by_id))
+                                .execute(connection)?;
+                        }
+
+                        diesel::delete(users::table)
+                            .filter(users::id.eq(by_id))
+                            .execute(connection)?;
+                        Ok(())
+                    })
+                })
+            }
+
+            fn remove_post(&self, by_id: i32) -> Result<(), Error> {
+                self.run(|connection| {
+                    connection.transaction::<_, DieselError, _>(|connection| {
+                        diesel::delete(post_revisions::table)
+                            .filter(post_revisions::post_id.eq(by_id))
+                            .execute(connection)?;
+                        diesel::delete(posts::table)
+                            .filter(posts::id.eq(by_id))
+                            .execute(connection)
+                    })
+                })?;
+                Ok(())
+            }
+
+            fn remove_posts_deleted_before(&self, before: NaiveDateTime) -> Result<usize, Error> {
+                self.run(|connection| {
+                    connection.transaction::<_, DieselError, _>(|connection| {
+                        diesel::delete(post_revisions::table)
+                            .filter(
+                                post_revisions::post_id.eq_any(
+                                    posts::table
+                                        .filter(posts::deleted_on.le(before))
+                                        .select(posts::id),
+                                ),
+                            )
+                            .execute(connection)?;
+                        diesel::delete(posts::table)
+                            .filter(posts::deleted_on.le(before))
+                            .execute(connection)
+                    })
+                })
+            }
+
+            fn remove_session_by_key(&self, by_digest: Vec<u8>) -> Result<(), Error> {
+                use crate::schema::sessions::dsl::{key_digest, sessions};
+                self.run(|connection| {
+                    diesel::delete(sessions)
+                        .filter(key_digest.eq(by_digest))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn remove_user_session(&self, by_user_id: i32, by_id: i32) -> Result<(), Error> {
+                use crate::schema::sessions::dsl::{id, sessions, user_id};
+                self.run(|connection| {
+                    diesel::delete(sessions)
+                        .filter(user_id.eq(by_user_id))
+                        .filter(id.eq(by_id))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn remove_user_sessions_except(
+                &self,
+                by_user_id: i32,
+                except_id: i32,
+            ) -> Result<(), Error> {
+                use crate::schema::sessions::dsl::{id, sessions, user_id};
+                self.run(|connection| {
+                    diesel::delete(sessions)
+                        .filter(user_id.eq(by_user_id))
+                        .filter(id.ne(except_id))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn remove_user_sessions(&self, by_user_id: i32) -> Result<(), Error> {
+                use crate::schema::sessions::dsl::{sessions, user_id};
+                self.run(|connection| {
+                    diesel::delete(sessions)
+                        .filter(user_id.eq(by_user_id))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn remove_expired_sessions(&self, now: NaiveDateTime) -> Result<usize, Error> {
+                use crate::schema::sessions::dsl::{expires_on, sessions};
+                self.run(|connection| {
+                    diesel::delete(sessions)
+                        .filter(expires_on.le(now))
+                        .execute(connection)
+                })
+            }
+
+            fn remove_email_verifications_by_user_id(&self, by_user_id: i32) -> Result<(), Error> {
+                use crate::schema::email_verifications::dsl::{email_verifications, user_id};
+                self.run(|connection| {
+                    diesel::delete(email_verifications)
+                        .filter(user_id.eq(by_user_id))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn remove_expired_email_verifications(
+                &self,
+                now: NaiveDateTime,
+            ) -> Result<usize, Error> {
+                use crate::schema::email_verifications::dsl::{email_verifications, expires_on};
+                self.run(|connection| {
+                    diesel::delete(email_verifications)
+                        .filter(expires_on.le(now))
+                        .execute(connection)
+                })
+            }
+
+            fn remove_password_resets_by_user_id(&self, by_user_id: i32) -> Result<(), Error> {
+                use crate::schema::password_resets::dsl::{password_resets, user_id};
+                self.run(|connection| {
+                    diesel::delete(password_resets)
+                        .filter(user_id.eq(by_user_id))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn remove_expired_password_resets(&self, now: NaiveDateTime) -> Result<usize, Error> {
+                use crate::schema::password_resets::dsl::{expires_on, password_resets};
+                self.run(|connection| {
+                    diesel::delete(password_resets)
+                        .filter(expires_on.le(now))
+                        .execute(connection)
+                })
+            }
+
+            fn remove_login_challenge_by_token(&self, by_digest: Vec<u8>) -> Result<(), Error> {
+                use crate::schema::login_challenges::dsl::{login_challenges, token_digest};
+                self.run(|connection| {
+                    diesel::delete(login_challenges)
+                        .filter(token_digest.eq(by_digest))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+
+            fn remove_expired_login_challenges(&self, now: NaiveDateTime) -> Result<usize, Error> {
+                use crate::schema::login_challenges::dsl::{expires_on, login_challenges};
+                self.run(|connection| {
+                    diesel::delete(login_challenges)
+                        .filter(expires_on.le(now))
+                        .execute(connection)
+                })
+            }
+
+            fn remove_recovery_code(
+                &self,
+                by_user_id: i32,
+                by_digest: Vec<u8>,
+            ) -> Result<bool, Error> {
+                use crate::schema::recovery_codes::dsl::{code_digest, recovery_codes, user_id};
+                // only one request gets to delete the row, which is what makes
+                // every code single-use
+                let removed = self.run(|connection| {
+                    diesel::delete(recovery_codes)
+                        .filter(user_id.eq(by_user_id))
+                        .filter(code_digest.eq(by_digest))
+                        .execute(connection)
+                })?;
+                Ok(removed > 0)
+            }
+
+            fn remove_recovery_codes_by_user_id(&self, by_user_id: i32) -> Result<(), Error> {
+                use crate::schema::recovery_codes::dsl::{recovery_codes, user_id};
+                self.run(|connection| {
+                    diesel::delete(recovery_codes)
+                        .filter(user_id.eq(by_user_id))
+                        .execute(connection)
+                })?;
+                Ok(())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "mysql")]
+impl_database!(
+    diesel::mysql::MysqlConnection,
+    "migrations/mysql",
+    fulltext_search: true,
+    year: "YEAR(created_on)",
+    month: "MONTH(created_on)"
+);
+#[cfg(feature = "postgres")]
+impl_database!(
+    diesel::pg::PgConnection,
+    "migrations/postgres",
+    fulltext_search: false,
+    year: "CAST(EXTRACT(YEAR FROM created_on) AS INTEGER)",
+    month: "CAST(EXTRACT(MONTH FROM created_on) AS INTEGER)"
+);
+#[cfg(feature = "sqlite")]
+impl_database!(
+    diesel::sqlite::SqliteConnection,
+    "migrations/sqlite",
+    fulltext_search: false,
+    year: "CAST(strftime('%Y', created_on) AS INTEGER)",
+    month: "CAST(strftime('%m', created_on) AS INTEGER)"
+);
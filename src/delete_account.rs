@@ -0,0 +1,109 @@
+use rocket::{get, http::CookieJar, post, FromForm, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    csrf::{CsrfForm, CsrfProtected},
+};
+
+#[derive(Serialize, Debug)]
+struct DeleteAccountLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    error: Option<String>,
+    success: Option<String>,
+}
+
+impl DeleteAccountLayoutContext {
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+    ) -> Result<DeleteAccountLayoutContext, Error> {
+        let error = match state.get_valid_user_info(jar)? {
+            Some(_) => None,
+            None => Some("You need to log in first!".to_string()),
+        };
+
+        Ok(DeleteAccountLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            error,
+            success: None,
+        })
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_success(mut self, success: Option<String>) -> Self {
+        self.success = success;
+        self
+    }
+}
+
+#[get("/account/delete")]
+pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "delete_account",
+        DeleteAccountLayoutContext::new(state, jar)?,
+    ))
+}
+
+#[derive(FromForm)]
+pub struct DeleteAccountForm {
+    pub csrf_token: String,
+    pub password: String,
+    // posts are kept up without an author unless asked otherwise
+    pub delete_posts: bool,
+}
+
+impl CsrfProtected for DeleteAccountForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/account/delete", data = "<data>")]
+pub fn post(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    data: CsrfForm<DeleteAccountForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        if data.password.is_empty() {
+            break 'requirements Some("All fields are required!");
+        }
+        // a session left open on a shared computer shouldn't be enough
+        if !state
+            .password_hasher()
+            .verify(&data.password, &user.password)?
+        {
+            break 'requirements Some("Password is incorrect!");
+        }
+
+        state.database().remove_user(user.id, !data.delete_posts)?;
+        state.remove_session_cookie(jar);
+
+        None
+    } {
+        return Ok(Template::render(
+            "delete_account",
+            DeleteAccountLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    // nobody is logged in anymore, which isn't an error this time
+    Ok(Template::render(
+        "delete_account",
+        DeleteAccountLayoutContext::new(state, jar)?
+            .with_error(None)
+            .with_success(Some("Your account has been deleted.".to_string())),
+    ))
+}
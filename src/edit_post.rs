@@ -0,0 +1,158 @@
+use rocket::{get, http::CookieJar, post, FromForm, State};
+use rocket_dyn_templates::Template;
+use serde::{self, Serialize};
+
+use crate::{
+    application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    csrf::{CsrfForm, CsrfProtected},
+    models::PostEdit,
+    post::visible_post,
+};
+
+#[derive(Serialize, Debug)]
+struct EditPostLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    id: i32,
+    previous_title: String,
+    previous_description: String,
+    previous_content: String,
+
+    error: Option<String>,
+    success: Option<String>,
+}
+
+impl EditPostLayoutContext {
+    // the form starts out filled in with the post as it is now
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+        id: i32,
+    ) -> Result<EditPostLayoutContext, Error> {
+        let post = visible_post(state, id)?.post;
+
+        let error = match state.get_valid_user_info(jar)? {
+            None => Some("You need to log in first!".to_string()),
+            Some(user) if post.author != Some(user.id) => {
+                Some("Only the author can edit this post!".to_string())
+            }
+            Some(_) => None,
+        };
+
+        Ok(EditPostLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            id,
+            previous_title: post.title,
+            previous_description: post.description,
+            previous_content: post.content,
+            error,
+            success: None,
+        })
+    }
+
+    pub fn with_previous_data(mut self, data: &EditPostForm) -> Self {
+        // all three of these are HTML escaped by handlebars
+        self.previous_title = data.title.clone();
+        self.previous_content = data.content.clone();
+        self.previous_description = data.description.clone();
+        self
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_success(mut self, success: Option<String>) -> Self {
+        self.success = success;
+        self
+    }
+}
+
+#[get("/post/<id>/edit")]
+pub fn get(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    id: i32,
+) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "edit_post",
+        EditPostLayoutContext::new(state, jar, id)?,
+    ))
+}
+
+#[derive(FromForm)]
+#[allow(clippy::module_name_repetitions)]
+pub struct EditPostForm {
+    pub csrf_token: String,
+    pub title: String,
+    pub description: String,
+    pub content: String,
+}
+
+impl CsrfProtected for EditPostForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+impl EditPostForm {
+    pub fn all_fields_populated(&self) -> bool {
+        !self.title.is_empty() && !self.description.is_empty() && !self.content.is_empty()
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/post/<id>/edit", data = "<data>")]
+pub fn post(
+    jar: &CookieJar,
+    state: &State<SharedState>,
+    id: i32,
+    data: CsrfForm<EditPostForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some(user_info) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        let post = visible_post(state, id)?.post;
+        if post.author != Some(user_info.id) {
+            break 'requirements Some("Only the author can edit this post!");
+        }
+        if !data.all_fields_populated() {
+            break 'requirements Some("All fields are required!");
+        }
+        // every edit leaves a revision behind, so empty ones aren't let through
+        if data.title == post.title
+            && data.description == post.description
+            && data.content == post.content
+        {
+            break 'requirements Some("Nothing was changed!");
+        }
+
+        // the same escaping as for new posts applies when they are shown
+        state.database().edit_post(
+            id,
+            PostEdit {
+                title: &data.title,
+                description: &data.description,
+                content: &data.content,
+                edited_on: chrono::offset::Utc::now().naive_utc(),
+            },
+        )?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "edit_post",
+            EditPostLayoutContext::new(state, jar, id)?
+                .with_previous_data(&data)
+                .with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "edit_post",
+        EditPostLayoutContext::new(state, jar, id)?.with_success(Some("Post updated!".to_string())),
+    ))
+}
@@ -0,0 +1,165 @@
+use chrono::NaiveDateTime;
+use rocket::{
+    get,
+    http::{ContentType, CookieJar, Header},
+    response::Redirect,
+    Responder, State,
+};
+use serde::Serialize;
+
+use crate::{
+    application::{Error, ErrorResponder, SharedState},
+    models::{Post, PostRevision, Session, User},
+};
+
+// Everything stored about a user, in a form they can take elsewhere. Post
+// contents are kept as the Markdown they were written in, and are also in the
+// archive as files of their own.
+#[derive(Serialize, Debug)]
+struct AccountExport {
+    exported_on: NaiveDateTime,
+    profile: ProfileData,
+    sessions: Vec<SessionData>,
+    posts: Vec<Post>,
+    // earlier versions of the posts above
+    post_revisions: Vec<PostRevision>,
+}
+
+#[derive(Serialize, Debug)]
+struct ProfileData {
+    id: i32,
+    username: String,
+    email: String,
+    email_verified: bool,
+    is_admin: bool,
+    two_factor_enabled: bool,
+}
+
+impl ProfileData {
+    // the password hash and the TOTP secret are of no use to anyone outside
+    // of the application
+    fn from_user(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            email_verified: user.email_verified,
+            is_admin: user.is_admin,
+            two_factor_enabled: user.totp_enabled,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct SessionData {
+    created_on: NaiveDateTime,
+    last_seen: NaiveDateTime,
+    expires_on: NaiveDateTime,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+}
+
+impl SessionData {
+    // the key digest stays out, it is only good for looking sessions up
+    fn from_session(session: Session) -> Self {
+        Self {
+            created_on: session.created_on,
+            last_seen: session.last_seen,
+            expires_on: session.expires_on,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+        }
+    }
+}
+
+// A tar archive of the given files, which any system can unpack without
+// anything special installed. Names have to be shorter than 100 bytes.
+fn tar_archive(files: &[(String, Vec<u8>)], modified_on: NaiveDateTime) -> Vec<u8> {
+    const BLOCK: usize = 512;
+    let mut archive = Vec::new();
+    for (name, contents) in files {
+        let mut header = [0u8; BLOCK];
+        let mut field = |offset: usize, value: &[u8]| {
+            header[offset..offset + value.len()].copy_from_slice(value);
+        };
+        field(0, name.as_bytes());
+        field(100, b"0000644\0");
+        field(108, b"0000000\0");
+        field(116, b"0000000\0");
+        field(124, format!("{:011o}\0", contents.len()).as_bytes());
+        field(
+            136,
+            format!("{:011o}\0", modified_on.timestamp().max(0)).as_bytes(),
+        );
+        // the checksum is counted as if its own field were spaces
+        field(148, b"        ");
+        field(156, b"0");
+        field(257, b"ustar\0");
+        field(263, b"00");
+        let checksum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+        header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(contents);
+        archive.resize(archive.len().next_multiple_of(BLOCK), 0);
+    }
+    // the end of the archive is marked by two empty blocks
+    archive.resize(archive.len() + 2 * BLOCK, 0);
+    archive
+}
+
+#[derive(Responder)]
+pub enum ExportResponse {
+    Download((ContentType, Vec<u8>), Header<'static>),
+    // the account page tells visitors that aren't logged in to do so
+    NotLoggedIn(Redirect),
+}
+
+#[get("/account/export")]
+pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<ExportResponse, ErrorResponder> {
+    let Some(user) = state.get_valid_user_info(jar)? else {
+        return Ok(ExportResponse::NotLoggedIn(Redirect::to("/account")));
+    };
+
+    let posts = state.database().get_posts_by_author(user.id)?;
+    let post_revisions = state.database().get_post_revisions_by_author(user.id)?;
+    let now = chrono::offset::Utc::now().naive_utc();
+
+    let mut files: Vec<(String, Vec<u8>)> = posts
+        .iter()
+        .map(|post| {
+            (
+                format!("posts/{}.md", post.id),
+                post.content.clone().into_bytes(),
+            )
+        })
+        .collect();
+
+    let export = AccountExport {
+        exported_on: now,
+        sessions: state
+            .database()
+            .get_sessions_by_user_id(user.id)?
+            .into_iter()
+            .map(SessionData::from_session)
+            .collect(),
+        post_revisions,
+        posts,
+        profile: ProfileData::from_user(user),
+    };
+
+    let json =
+        serde_json::to_vec_pretty(&export).map_err(|error| Error::Export(error.to_string()))?;
+    files.insert(0, ("account.json".to_string(), json));
+
+    Ok(ExportResponse::Download(
+        (
+            ContentType::new("application", "x-tar"),
+            tar_archive(&files, now),
+        ),
+        Header::new(
+            "Content-Disposition",
+            "attachment; filename=\"account.tar\"",
+        ),
+    ))
+}
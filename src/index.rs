@@ -1,14 +1,35 @@
+use chrono::NaiveDateTime;
 use rocket::{get, http::CookieJar, State};
 use rocket_dyn_templates::Template;
 use serde::Serialize;
 
 use crate::{
     application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
-    models::Post,
+    models::{PostCursor, PostWithAuthor},
+    post::author_name,
 };
 
+const CURSOR_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+// how a cursor looks in the URL, e.g. 2023-03-17T08:43:12.5_42
+pub fn format_cursor(cursor: PostCursor) -> String {
+    format!(
+        "{}_{}",
+        cursor.created_on.format(CURSOR_TIME_FORMAT),
+        cursor.id
+    )
+}
+
+pub fn parse_cursor(value: &str) -> Option<PostCursor> {
+    let (created_on, id) = value.rsplit_once('_')?;
+    Some(PostCursor {
+        created_on: NaiveDateTime::parse_from_str(created_on, CURSOR_TIME_FORMAT).ok()?,
+        id: id.parse().ok()?,
+    })
+}
+
 #[derive(Serialize, Debug)]
-struct ShortPostData {
+pub struct ShortPostData {
     author: String,
     human_readable_creation_time: String,
     title: String,
@@ -17,14 +38,9 @@ struct ShortPostData {
 }
 
 impl ShortPostData {
-    fn from_post(state: &State<SharedState>, post: Post) -> Result<Self, Error> {
-        let author = state
-            .lock()
-            .unwrap()
-            .database()
-            .get_user_by_id(post.author)?
-            .ok_or(Error::PostHasInvalidUserId)?
-            .username;
+    pub fn from_post(post: PostWithAuthor) -> Result<Self, Error> {
+        let author = author_name(&post)?;
+        let post = post.post;
 
         let human_readable_creation_time =
             post.created_on.format("%d. %m. %Y. %H:%M:%S").to_string();
@@ -47,26 +63,61 @@ struct IndexLayoutContext {
     base_context: BaseLayoutContext,
 
     posts: Vec<ShortPostData>,
+    // set when there are more posts in that direction
+    newer_cursor: Option<String>,
+    older_cursor: Option<String>,
 }
 
 impl IndexLayoutContext {
-    pub fn new(state: &State<SharedState>, jar: &CookieJar) -> Result<IndexLayoutContext, Error> {
-        // sticking these two together won't release the mutex after retrieving
-        // the posts, making the map stuck
-        let posts = state.lock().unwrap().database().get_latest_x_posts(10)?;
+    // A page of posts right before or after the cursor, or the latest ones.
+    // Cursors that can't be parsed lead back to the latest posts.
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+        before: Option<&str>,
+        after: Option<&str>,
+    ) -> Result<IndexLayoutContext, Error> {
+        let database = state.database();
+        let count = i64::from(state.config().posts_per_page.max(1));
+
+        let posts = match after.and_then(parse_cursor) {
+            Some(after) => database.get_posts_after(after, count)?,
+            None => database.get_posts_before(before.and_then(parse_cursor), count)?,
+        };
+
+        let newer_cursor = match posts.first().map(|first| first.post.cursor()) {
+            Some(first) if !database.get_posts_after(first, 1)?.is_empty() => {
+                Some(format_cursor(first))
+            }
+            _ => None,
+        };
+        let older_cursor = match posts.last().map(|last| last.post.cursor()) {
+            Some(last) if !database.get_posts_before(Some(last), 1)?.is_empty() => {
+                Some(format_cursor(last))
+            }
+            _ => None,
+        };
+
         let posts = posts
             .into_iter()
-            .map(|post| ShortPostData::from_post(state, post))
+            .map(ShortPostData::from_post)
             .collect::<Result<Vec<ShortPostData>, Error>>()?;
         Ok(IndexLayoutContext {
             base_context: BaseLayoutContext::new(state, jar)?,
             posts,
+            newer_cursor,
+            older_cursor,
         })
     }
 }
 
-#[get("/")]
-pub fn get(jar: &CookieJar, state: &State<SharedState>) -> Result<Template, ErrorResponder> {
-    let context = IndexLayoutContext::new(state, jar)?;
+#[get("/?<before>&<after>")]
+pub fn get(
+    jar: &CookieJar,
+    state: &State<SharedState>,
+    before: Option<&str>,
+    after: Option<&str>,
+) -> Result<Template, ErrorResponder> {
+    let context = IndexLayoutContext::new(state, jar, before, after)?;
     Ok(Template::render("index", context))
 }
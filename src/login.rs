@@ -1,14 +1,21 @@
-use rand::{rngs::StdRng, Fill, SeedableRng};
-use rocket::{
-    form::Form,
-    get,
-    http::{Cookie, CookieJar},
-    post, FromForm, State,
-};
+use std::iter;
+
+use chrono::NaiveDateTime;
+use rocket::{get, http::CookieJar, post, FromForm, State};
 use rocket_dyn_templates::Template;
 use serde::{self, Serialize};
 
-use crate::application::{BaseLayoutContext, Error, ErrorResponder, SharedState};
+use crate::{
+    application::{
+        generate_key, token_digest, BaseLayoutContext, ClientInfo, Error, ErrorResponder,
+        SharedState,
+    },
+    csrf::{CsrfForm, CsrfProtected},
+    models::{canonical, LoginChallenge, NewLockout, NewLoginChallenge},
+    throttle::{Refusal, ThrottleKey},
+    two_factor,
+    verify::send_verification_email,
+};
 
 #[derive(Serialize, Debug)]
 struct LoginLayoutContext {
@@ -59,21 +66,77 @@ pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, Erro
 #[derive(FromForm)]
 #[allow(clippy::module_name_repetitions)]
 pub struct LoginForm {
+    pub csrf_token: String,
     pub username_or_email: String,
     pub password: String,
 }
 
+impl CsrfProtected for LoginForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
 impl LoginForm {
     pub fn all_fields_populated(&self) -> bool {
         !self.username_or_email.is_empty() && !self.password.is_empty()
     }
 }
 
-fn generate_session_key() -> Result<Vec<u8>, rand::Error> {
-    let mut session_key = Vec::new();
-    session_key.resize(32, 0u8);
-    session_key.try_fill(&mut StdRng::from_entropy())?;
-    Ok(session_key)
+const INVALID_CREDENTIALS: &str = "Invalid username/e-mail or password provided.";
+const INVALID_CODE: &str = "Invalid code provided.";
+const EXPIRED_LOGIN: &str = "Your login has expired, please log in again.";
+
+// holds the token of a login that is waiting for the second factor
+const LOGIN_CHALLENGE_COOKIE: &str = "login-challenge";
+
+// whether attempts from the client's address, or for the account if it is
+// already known, have to wait
+fn throttle_refusal(
+    state: &SharedState,
+    client: &ClientInfo,
+    account_key: Option<&ThrottleKey>,
+    now: NaiveDateTime,
+) -> Option<Refusal> {
+    let address_key = client.ip_address.clone().map(ThrottleKey::Address);
+    address_key
+        .iter()
+        .chain(account_key)
+        .find_map(|key| state.login_throttle().check(key, now))
+}
+
+// Counts a failed attempt against the account and the client address, and
+// records any lockout that causes for the administrators.
+fn record_failure(
+    state: &SharedState,
+    client: &ClientInfo,
+    account_key: &ThrottleKey,
+    now: NaiveDateTime,
+) -> Result<(), Error> {
+    let address_key = client.ip_address.clone().map(ThrottleKey::Address);
+
+    for key in iter::once(account_key.clone()).chain(address_key) {
+        let Some(lockout) = state.login_throttle().record_failure(key.clone(), now) else {
+            continue;
+        };
+
+        let user_id = match key {
+            ThrottleKey::Account(user_id) => Some(user_id),
+            // lockouts of names that don't belong to anyone aren't worth keeping
+            ThrottleKey::UnknownAccount(_) | ThrottleKey::Email(_) => continue,
+            ThrottleKey::Address(_) => None,
+        };
+
+        state.database().create_lockout(NewLockout {
+            user_id,
+            ip_address: client.ip_address.as_deref(),
+            failed_attempts: i32::try_from(lockout.failed_attempts).unwrap_or(i32::MAX),
+            locked_on: now,
+            locked_until: lockout.locked_until,
+        })?;
+    }
+
+    Ok(())
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -81,45 +144,104 @@ fn generate_session_key() -> Result<Vec<u8>, rand::Error> {
 pub fn post(
     jar: &CookieJar,
     state: &State<SharedState>,
-    data: Form<LoginForm>,
+    client: ClientInfo,
+    data: CsrfForm<LoginForm>,
 ) -> Result<Template, ErrorResponder> {
+    let now = chrono::offset::Utc::now().naive_utc();
+    let refusal =
+        |account_key: Option<&ThrottleKey>| throttle_refusal(state, &client, account_key, now);
+
     if let Some(error_message) = 'requirements: {
         if !data.all_fields_populated() {
-            break 'requirements Some("All fields are required!");
+            break 'requirements Some("All fields are required!".to_string());
+        }
+
+        // refused attempts don't get as far as checking the password, that's
+        // the expensive part and the one being guessed
+        if let Some(refusal) = refusal(None) {
+            break 'requirements Some(refusal.message(now));
         }
 
         let user_by_name = state
-            .lock()
-            .unwrap()
             .database()
             .get_user_by_username(&data.username_or_email)?;
 
         let user_by_email = state
-            .lock()
-            .unwrap()
             .database()
             .get_user_by_email(&data.username_or_email)?;
 
-        let Some(user) = user_by_name.or(user_by_email) else {
-            break 'requirements Some("Invalid username/e-mail or password provided.");
+        let user = user_by_name.or(user_by_email);
+
+        let account_key = match &user {
+            Some(user) => ThrottleKey::Account(user.id),
+            None => ThrottleKey::UnknownAccount(canonical(&data.username_or_email)),
         };
+        if let Some(refusal) = refusal(Some(&account_key)) {
+            break 'requirements Some(refusal.message(now));
+        }
 
-        if !bcrypt::verify(&data.password, &user.password).map_err(Error::Bcrypt)? {
-            break 'requirements Some("Invalid username/e-mail or password provided.");
+        let password_hasher = state.password_hasher();
+        let password_matches = if let Some(user) = &user {
+            password_hasher.verify(&data.password, &user.password)?
+        } else {
+            password_hasher.verify_dummy(&data.password)?;
+            false
+        };
+
+        let Some(user) = user.filter(|_| password_matches) else {
+            record_failure(state, &client, &account_key, now)?;
+            // let the user know right away if they have to wait now
+            break 'requirements Some(refusal(Some(&account_key)).map_or_else(
+                || INVALID_CREDENTIALS.to_string(),
+                |refusal| refusal.message(now),
+            ));
+        };
+
+        // hashes made with an older algorithm or cost can only be replaced
+        // while the plain password is at hand
+        if password_hasher.needs_rehash(&user.password) {
+            let hashed_password = password_hasher.hash(&data.password)?;
+            state
+                .database()
+                .set_user_password(user.id, &hashed_password)?;
         }
 
-        let session_key = generate_session_key().map_err(Error::Rand)?;
+        // The password alone doesn't count as a successful login yet, or else
+        // it could be used to clear the failed attempts at guessing the code.
+        if user.totp_enabled {
+            start_login_challenge(state, jar, user.id, now)?;
+            return Ok(Template::render(
+                "login_code",
+                LoginLayoutContext::new(state, jar)?,
+            ));
+        }
 
-        jar.add(Cookie::new("session-key", hex::encode(session_key.clone())));
+        state.login_throttle().record_success(&account_key);
 
-        state
-            .lock()
-            .unwrap()
-            .database()
-            .create_session(crate::models::Session {
-                session_key,
-                user_id: user.id,
-            })?;
+        if !user.email_verified {
+            // the link was sent when it was made, a full lifetime before it expires
+            let resend_after = state.config().email_verification_lifetime()
+                - state.config().email_verification_resend_interval();
+            let recently_sent = state
+                .database()
+                .get_latest_email_verification_by_user_id(user.id)?
+                .map_or(false, |verification| {
+                    verification.expires_on > now + resend_after
+                });
+            if recently_sent {
+                break 'requirements Some(
+                    "Please confirm your e-mail address first, check your inbox for the link."
+                        .to_string(),
+                );
+            }
+
+            send_verification_email(state, &user)?;
+            break 'requirements Some(
+                "Please confirm your e-mail address first, we've sent you a new link.".to_string(),
+            );
+        }
+
+        start_session(state, jar, &client, user.id)?;
 
         None
     } {
@@ -127,7 +249,7 @@ pub fn post(
             "login",
             LoginLayoutContext::new(state, jar)?
                 .with_previous_username_or_email(Some(data.username_or_email.clone()))
-                .with_error(Some(error_message.to_owned())),
+                .with_error(Some(error_message)),
         ));
     }
 
@@ -137,3 +259,151 @@ pub fn post(
             .with_success(Some("Logged in successfully!".to_string())),
     ))
 }
+
+fn start_session(
+    state: &SharedState,
+    jar: &CookieJar,
+    client: &ClientInfo,
+    user_id: i32,
+) -> Result<(), Error> {
+    let session_key = generate_key()?;
+
+    state.add_session_cookie(jar, &session_key);
+
+    state
+        .database()
+        .create_session(state.new_session(&session_key, user_id, client))
+}
+
+// Remembers that the password was right, the session is only started once the
+// code from the authenticator app or a recovery code is entered as well.
+fn start_login_challenge(
+    state: &SharedState,
+    jar: &CookieJar,
+    user_id: i32,
+    now: NaiveDateTime,
+) -> Result<(), Error> {
+    let token = generate_key()?;
+
+    state.database().create_login_challenge(NewLoginChallenge {
+        token_digest: token_digest(&token),
+        user_id,
+        expires_on: now + state.config().two_factor_login_lifetime(),
+    })?;
+    jar.add(state.cookie(LOGIN_CHALLENGE_COOKIE, hex::encode(token)));
+
+    Ok(())
+}
+
+// the challenge the browser's cookie belongs to, as long as it is still usable
+fn find_login_challenge(
+    state: &SharedState,
+    jar: &CookieJar,
+    now: NaiveDateTime,
+) -> Result<Option<LoginChallenge>, Error> {
+    let token = jar
+        .get(LOGIN_CHALLENGE_COOKIE)
+        .and_then(|cookie| hex::decode(cookie.value()).ok());
+    let Some(token) = token else {
+        return Ok(None);
+    };
+
+    let challenge = state
+        .database()
+        .get_login_challenge_by_token(token_digest(&token))?;
+    Ok(challenge.filter(|challenge| challenge.expires_on > now))
+}
+
+fn end_login_challenge(
+    state: &SharedState,
+    jar: &CookieJar,
+    challenge: &LoginChallenge,
+) -> Result<(), Error> {
+    state
+        .database()
+        .remove_login_challenge_by_token(challenge.token_digest.clone())?;
+    jar.remove(state.cookie(LOGIN_CHALLENGE_COOKIE, String::new()));
+    Ok(())
+}
+
+#[get("/login/code")]
+pub fn get_code(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, ErrorResponder> {
+    let now = chrono::offset::Utc::now().naive_utc();
+    let context = LoginLayoutContext::new(state, jar)?;
+    let context = match find_login_challenge(state, jar, now)? {
+        Some(_) => context,
+        None => context.with_error(Some(EXPIRED_LOGIN.to_string())),
+    };
+
+    Ok(Template::render("login_code", context))
+}
+
+#[derive(FromForm)]
+pub struct LoginCodeForm {
+    pub csrf_token: String,
+    pub code: String,
+}
+
+impl CsrfProtected for LoginCodeForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/login/code", data = "<data>")]
+pub fn post_code(
+    jar: &CookieJar,
+    state: &State<SharedState>,
+    client: ClientInfo,
+    data: CsrfForm<LoginCodeForm>,
+) -> Result<Template, ErrorResponder> {
+    let now = chrono::offset::Utc::now().naive_utc();
+
+    if let Some(error_message) = 'requirements: {
+        let Some(challenge) = find_login_challenge(state, jar, now)? else {
+            break 'requirements Some(EXPIRED_LOGIN.to_string())
+        };
+        if data.code.is_empty() {
+            break 'requirements Some("All fields are required!".to_string());
+        }
+
+        // guessing codes is throttled the same way as guessing passwords
+        let account_key = ThrottleKey::Account(challenge.user_id);
+        if let Some(refusal) = throttle_refusal(state, &client, Some(&account_key), now) {
+            break 'requirements Some(refusal.message(now));
+        }
+
+        let user = state.database().get_user_by_id(challenge.user_id)?;
+        let Some(user) = user.filter(|user| user.totp_enabled) else {
+            // two-factor authentication was turned off in the meantime
+            end_login_challenge(state, jar, &challenge)?;
+            break 'requirements Some(EXPIRED_LOGIN.to_string());
+        };
+
+        if !two_factor::check_code(state, &user, &data.code, now)? {
+            record_failure(state, &client, &account_key, now)?;
+            break 'requirements Some(
+                throttle_refusal(state, &client, Some(&account_key), now)
+                    .map_or_else(|| INVALID_CODE.to_string(), |refusal| refusal.message(now)),
+            );
+        }
+
+        state.login_throttle().record_success(&account_key);
+        end_login_challenge(state, jar, &challenge)?;
+        start_session(state, jar, &client, user.id)?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "login_code",
+            LoginLayoutContext::new(state, jar)?.with_error(Some(error_message)),
+        ));
+    }
+
+    Ok(Template::render(
+        "login_code",
+        LoginLayoutContext::new(state, jar)?
+            .with_success(Some("Logged in successfully!".to_string())),
+    ))
+}
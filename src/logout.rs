@@ -1,29 +1,24 @@
-use rocket::{
-    get,
-    http::{Cookie, CookieJar},
-    response::Redirect,
-    State,
-};
+use rocket::{http::CookieJar, post, response::Redirect, State};
 
-use crate::application::{ErrorResponder, SharedState};
+use crate::{
+    application::{token_digest, ErrorResponder, SharedState},
+    csrf::{CsrfForm, CsrfTokenForm},
+};
 
-#[get("/logout")]
-pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Redirect, ErrorResponder> {
-    jar.get("session-key")
-        .and_then(|encoded_key| {
-            hex::decode(encoded_key.value())
-                .map(|session_key| {
-                    state
-                        .lock()
-                        .unwrap()
-                        .database()
-                        .remove_session_by_key(session_key)
-                })
-                .ok()
-        })
-        .transpose()?;
+// a POST so that other sites can't log users out with a simple link
+#[post("/logout", data = "<_csrf>")]
+pub fn post(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    _csrf: CsrfForm<CsrfTokenForm>,
+) -> Result<Redirect, ErrorResponder> {
+    if let Some(session_key) = state.session_key_from_cookie(jar) {
+        state
+            .database()
+            .remove_session_by_key(token_digest(&session_key))?;
+    }
 
-    jar.remove(Cookie::named("session-key"));
+    state.remove_session_cookie(jar);
 
     Ok(Redirect::to("/"))
 }
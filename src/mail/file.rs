@@ -0,0 +1,56 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use super::{Email, Mailer};
+use crate::Error;
+
+fn format_email(email: &Email) -> String {
+    format!(
+        "To: {}\nSubject: {}\n\n{}\n",
+        email.to, email.subject, email.body
+    )
+}
+
+pub struct StdoutMailer;
+
+impl Mailer for StdoutMailer {
+    fn send(&self, email: &Email) -> Result<(), Error> {
+        println!(
+            "----- e-mail -----\n{}------------------",
+            format_email(email)
+        );
+        Ok(())
+    }
+}
+
+// Each e-mail ends up in a file of its own, named after the time it was sent.
+pub struct FileMailer {
+    directory: PathBuf,
+    // tells apart e-mails sent within the same second
+    counter: AtomicU32,
+}
+
+impl FileMailer {
+    pub fn new(directory: PathBuf) -> Result<FileMailer, Error> {
+        fs::create_dir_all(&directory).map_err(|error| Error::Mail(error.to_string()))?;
+        Ok(FileMailer {
+            directory,
+            counter: AtomicU32::new(0),
+        })
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, email: &Email) -> Result<(), Error> {
+        let file_name = format!(
+            "{}-{}.txt",
+            chrono::offset::Utc::now().format("%Y-%m-%d-%H%M%S"),
+            self.counter.fetch_add(1, Ordering::Relaxed)
+        );
+        fs::write(self.directory.join(file_name), format_email(email))
+            .map_err(|error| Error::Mail(error.to_string()))
+    }
+}
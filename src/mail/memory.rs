@@ -0,0 +1,28 @@
+use std::sync::{Arc, Mutex};
+
+use super::{Email, Mailer};
+use crate::Error;
+
+// Keeps sent e-mails around so that tests can look at them. Clones share the
+// same list of e-mails.
+#[derive(Default, Clone)]
+pub struct MemoryMailer {
+    sent: Arc<Mutex<Vec<Email>>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> MemoryMailer {
+        MemoryMailer::default()
+    }
+
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl Mailer for MemoryMailer {
+    fn send(&self, email: &Email) -> Result<(), Error> {
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+mod file;
+#[cfg(test)]
+mod memory;
+mod smtp;
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+#[cfg(test)]
+pub use memory::MemoryMailer;
+
+use crate::{config::Config, Error};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), Error>;
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MailerKind {
+    // prints every e-mail, for development
+    Stdout,
+    // writes every e-mail into its own file in mail_directory
+    File,
+    Smtp,
+}
+
+pub fn from_config(config: &Config) -> Result<Box<dyn Mailer>, Error> {
+    Ok(match config.mailer {
+        MailerKind::Stdout => Box::new(file::StdoutMailer),
+        MailerKind::File => Box::new(file::FileMailer::new(PathBuf::from(
+            &config.mail_directory,
+        ))?),
+        MailerKind::Smtp => Box::new(smtp::SmtpMailer::new(config)?),
+    })
+}
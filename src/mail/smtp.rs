@@ -0,0 +1,71 @@
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, Message, SmtpTransport,
+    Transport,
+};
+
+use super::{Email, Mailer};
+use crate::{config::Config, Error};
+
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &Config) -> Result<SmtpMailer, Error> {
+        let Some(host) = &config.smtp_host else {
+            return Err(Error::Mail(
+                "smtp_host has to be set to use SMTP".to_string(),
+            ));
+        };
+
+        // implicit TLS by default, STARTTLS for servers that only offer that
+        let builder = if config.smtp_starttls {
+            SmtpTransport::starttls_relay(host)
+        } else {
+            SmtpTransport::relay(host)
+        }
+        .map_err(|error| Error::Mail(error.to_string()))?;
+
+        let builder = match config.smtp_port {
+            Some(port) => builder.port(port),
+            None => builder,
+        };
+
+        let builder = match (&config.smtp_username, &config.smtp_password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.clone(), password.clone()))
+            }
+            _ => builder,
+        };
+
+        Ok(SmtpMailer {
+            transport: builder.build(),
+            from: config
+                .mail_from
+                .parse()
+                .map_err(|_| Error::Mail(format!("invalid mail_from: {}", config.mail_from)))?,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: &Email) -> Result<(), Error> {
+        let to: Mailbox = email
+            .to
+            .parse()
+            .map_err(|_| Error::Mail(format!("invalid recipient: {}", email.to)))?;
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&email.subject)
+            .body(email.body.clone())
+            .map_err(|error| Error::Mail(error.to_string()))?;
+
+        self.transport
+            .send(&message)
+            .map_err(|error| Error::Mail(error.to_string()))?;
+        Ok(())
+    }
+}
@@ -3,47 +3,129 @@
 #![warn(clippy::pedantic)]
 #![deny(warnings)]
 #![allow(clippy::no_effect_underscore_binding)]
+mod account;
+mod admin;
 mod application;
+mod archive;
+mod cleanup;
+mod config;
+mod csrf;
 mod database;
+mod delete_account;
+mod edit_post;
+mod export;
 mod index;
 mod login;
 mod logout;
+mod mail;
 mod new_post;
+mod password;
+mod password_reset;
 mod post;
+mod post_history;
 mod register;
+mod search;
+mod sessions;
+mod throttle;
+mod totp;
+mod trash;
+mod two_factor;
+mod validation;
+mod verify;
 
 mod models;
 mod schema;
 
-use std::sync::Mutex;
+#[cfg(test)]
+mod tests;
 
 use dotenvy::dotenv;
-use rocket::{build, fs::FileServer, launch, routes};
+use rocket::{catchers, custom, figment::Figment, fs::FileServer, launch, routes, Build, Rocket};
 use rocket_dyn_templates::Template;
 
-use application::{Error, SharedStateData};
+use application::{Error, SharedState};
+use config::Config;
+use database::Database;
+use mail::Mailer;
+use password::PasswordHasher;
 
 #[launch]
 fn rocket() -> _ {
     dotenv().ok();
 
-    let shared_state = Mutex::new(SharedStateData::new().unwrap());
-    build()
+    let database = database::connect().unwrap();
+    database::migrate(database.as_ref()).unwrap();
+
+    let figment = rocket::Config::figment();
+    let mailer = mail::from_config(&figment.extract().unwrap()).unwrap();
+
+    build_rocket(figment, database, mailer)
+}
+
+fn build_rocket(
+    figment: Figment,
+    database: Box<dyn Database>,
+    mailer: Box<dyn Mailer>,
+) -> Rocket<Build> {
+    let config: Config = figment.extract().unwrap();
+    let tls_enabled = figment
+        .extract::<rocket::Config>()
+        .map_or(false, |rocket_config| rocket_config.tls_enabled());
+    let password_hasher = PasswordHasher::from_config(&config).unwrap();
+    let shared_state = SharedState::new(database, mailer, password_hasher, config, tls_enabled);
+
+    custom(figment)
         .mount("/", FileServer::from("static"))
         .mount(
             "/",
             routes![
                 index::get,
+                archive::get,
+                archive::get_month,
+                search::get,
                 register::get,
                 register::post,
                 login::get,
                 login::post,
-                logout::get,
+                login::get_code,
+                login::post_code,
+                logout::post,
                 new_post::get,
                 new_post::post,
                 post::get,
+                edit_post::get,
+                edit_post::post,
+                post_history::get,
+                post_history::get_diff,
+                trash::delete,
+                verify::get,
+                password_reset::get_forgot,
+                password_reset::post_forgot,
+                password_reset::get_reset,
+                password_reset::post_reset,
+                account::get,
+                account::post_password,
+                account::post_email,
+                account::post_username,
+                two_factor::get,
+                two_factor::post_setup,
+                two_factor::post_enable,
+                two_factor::post_recovery_codes,
+                two_factor::post_disable,
+                export::get,
+                delete_account::get,
+                delete_account::post,
+                sessions::get,
+                sessions::revoke,
+                sessions::revoke_others,
+                trash::get,
+                trash::restore,
+                trash::purge,
+                admin::lockouts,
             ],
         )
+        .register("/", catchers![csrf::forbidden])
         .attach(Template::fairing())
+        .attach(cleanup::fairing())
         .manage(shared_state)
 }
@@ -2,15 +2,29 @@ use chrono::NaiveDateTime;
 
 use diesel::prelude::*;
 use serde::Serialize;
+use unicode_normalization::UnicodeNormalization;
 
-use crate::schema::{posts, sessions, users};
+use crate::schema::{
+    email_verifications, lockouts, login_challenges, password_resets, post_revisions, posts,
+    recovery_codes, sessions, users,
+};
 
-#[derive(Queryable, Debug)]
+#[derive(Queryable, Debug, Clone)]
 pub struct User {
     pub id: i32,
     pub username: String,
     pub password: String,
     pub email: String,
+    pub is_admin: bool,
+    pub email_verified: bool,
+    // canonical forms of the above, users are looked up by these
+    pub username_canonical: String,
+    pub email_canonical: String,
+    // set while two-factor authentication is being set up or is enabled
+    pub totp_secret: Option<Vec<u8>>,
+    pub totp_enabled: bool,
+    // time step of the last code that was accepted, see totp::verify
+    pub totp_last_step: Option<i64>,
 }
 
 #[derive(Insertable)]
@@ -19,24 +33,139 @@ pub struct NewUser<'a> {
     pub username: &'a str,
     pub password: &'a str,
     pub email: &'a str,
+    pub username_canonical: String,
+    pub email_canonical: String,
 }
 
-#[derive(Insertable, Queryable)]
+impl<'a> NewUser<'a> {
+    pub fn new(username: &'a str, password: &'a str, email: &'a str) -> Self {
+        Self {
+            username,
+            password,
+            email,
+            username_canonical: canonical(username),
+            email_canonical: canonical(email),
+        }
+    }
+}
+
+// The form usernames and e-mail addresses are compared in, so that ones that
+// only differ in case or in how the same characters are encoded count as the
+// same. Stored next to the original, which is what gets shown.
+pub fn canonical(value: &str) -> String {
+    value
+        .nfkc()
+        .collect::<String>()
+        .to_lowercase()
+        .nfkc()
+        .collect()
+}
+
+#[derive(Queryable, Clone)]
 #[diesel(table_name = sessions)]
 pub struct Session {
-    pub session_key: Vec<u8>,
+    pub id: i32,
+    // SHA-256 of the key in the session cookie, the key itself is never stored
+    pub key_digest: Vec<u8>,
+    pub user_id: i32,
+    pub created_on: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    pub expires_on: NaiveDateTime,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sessions)]
+pub struct NewSession<'a> {
+    pub key_digest: Vec<u8>,
     pub user_id: i32,
+    pub created_on: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    pub expires_on: NaiveDateTime,
+    pub user_agent: Option<&'a str>,
+    pub ip_address: Option<&'a str>,
 }
 
-#[derive(Queryable, Debug, Serialize)]
+#[derive(Queryable, Debug, Serialize, Clone)]
 #[diesel(table_name = posts)]
 pub struct Post {
     pub id: i32,
-    pub author: i32,
+    // None once the author deleted their account but left their posts up
+    pub author: Option<i32>,
     pub created_on: NaiveDateTime,
     pub title: String,
     pub description: String,
     pub content: String,
+    // None until the post is edited for the first time
+    pub edited_on: Option<NaiveDateTime>,
+    // set while the post is in the trash
+    pub deleted_on: Option<NaiveDateTime>,
+}
+
+impl Post {
+    // when the current version of the post was written
+    pub fn last_changed_on(&self) -> NaiveDateTime {
+        self.edited_on.unwrap_or(self.created_on)
+    }
+
+    pub fn cursor(&self) -> PostCursor {
+        PostCursor {
+            created_on: self.created_on,
+            id: self.id,
+        }
+    }
+}
+
+// A post along with the username of its author, loaded with a join so that
+// listing posts doesn't take a query per post.
+#[derive(Debug, Clone)]
+pub struct PostWithAuthor {
+    pub post: Post,
+    // None when the post has no author (any more)
+    pub author_username: Option<String>,
+}
+
+impl From<(Post, Option<String>)> for PostWithAuthor {
+    fn from((post, author_username): (Post, Option<String>)) -> Self {
+        PostWithAuthor {
+            post,
+            author_username,
+        }
+    }
+}
+
+// How many posts that aren't in the trash were created in a month, see
+// Database::get_post_counts_by_month.
+#[derive(QueryableByName, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonthPostCount {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub year: i32,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub month: i32,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub count: i64,
+}
+
+// What the search page looks for, see Database::search_posts.
+#[derive(Debug, Clone, Default)]
+pub struct PostSearch {
+    // lowercase and made of letters and digits only, every one of them has to
+    // appear in the title, description or content
+    pub words: Vec<String>,
+    pub author: Option<i32>,
+    // from is inclusive, to isn't
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+// A position in the list of posts, for paging through it. Posts are ordered
+// by when they were created and then by id, so that posts created in the
+// same instant still have a fixed order and none get skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PostCursor {
+    pub created_on: NaiveDateTime,
+    pub id: i32,
 }
 
 #[derive(Insertable)]
@@ -48,3 +177,146 @@ pub struct NewPost<'a> {
     pub description: &'a str,
     pub content: &'a str,
 }
+
+// the new version of a post, the one it replaces is kept as a revision
+#[derive(AsChangeset)]
+#[diesel(table_name = posts)]
+pub struct PostEdit<'a> {
+    pub title: &'a str,
+    pub description: &'a str,
+    pub content: &'a str,
+    pub edited_on: NaiveDateTime,
+}
+
+#[derive(Queryable, Debug, Serialize, Clone)]
+#[diesel(table_name = post_revisions)]
+pub struct PostRevision {
+    pub id: i32,
+    pub post_id: i32,
+    // when this version was written, i.e. the post's creation or an edit
+    pub created_on: NaiveDateTime,
+    pub title: String,
+    pub description: String,
+    pub content: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = post_revisions)]
+pub struct NewPostRevision<'a> {
+    pub post_id: i32,
+    pub created_on: NaiveDateTime,
+    pub title: &'a str,
+    pub description: &'a str,
+    pub content: &'a str,
+}
+
+#[derive(Queryable, Clone)]
+#[diesel(table_name = lockouts)]
+pub struct Lockout {
+    pub id: i32,
+    // None when a client address was locked out rather than an account
+    pub user_id: Option<i32>,
+    pub ip_address: Option<String>,
+    pub failed_attempts: i32,
+    pub locked_on: NaiveDateTime,
+    pub locked_until: NaiveDateTime,
+}
+
+// A lockout along with the username of the account it is about, loaded with
+// a join so that listing lockouts doesn't take a query per lockout.
+#[derive(Clone)]
+pub struct LockoutWithUsername {
+    pub lockout: Lockout,
+    // None for lockouts of client addresses and of accounts that are gone
+    pub username: Option<String>,
+}
+
+impl From<(Lockout, Option<String>)> for LockoutWithUsername {
+    fn from((lockout, username): (Lockout, Option<String>)) -> Self {
+        LockoutWithUsername { lockout, username }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = lockouts)]
+pub struct NewLockout<'a> {
+    pub user_id: Option<i32>,
+    pub ip_address: Option<&'a str>,
+    pub failed_attempts: i32,
+    pub locked_on: NaiveDateTime,
+    pub locked_until: NaiveDateTime,
+}
+
+#[derive(Queryable, Clone)]
+#[diesel(table_name = email_verifications)]
+pub struct EmailVerification {
+    pub id: i32,
+    // SHA-256 of the token in the link that was sent out
+    pub token_digest: Vec<u8>,
+    pub user_id: i32,
+    pub expires_on: NaiveDateTime,
+    // the address the user wants to switch to, None when confirming the
+    // address they registered with
+    pub new_email: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = email_verifications)]
+pub struct NewEmailVerification<'a> {
+    pub token_digest: Vec<u8>,
+    pub user_id: i32,
+    pub expires_on: NaiveDateTime,
+    pub new_email: Option<&'a str>,
+}
+
+#[derive(Queryable, Clone)]
+#[diesel(table_name = password_resets)]
+pub struct PasswordReset {
+    pub id: i32,
+    // SHA-256 of the token in the link that was sent out
+    pub token_digest: Vec<u8>,
+    pub user_id: i32,
+    pub expires_on: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = password_resets)]
+pub struct NewPasswordReset {
+    pub token_digest: Vec<u8>,
+    pub user_id: i32,
+    pub expires_on: NaiveDateTime,
+}
+
+#[derive(Queryable, Clone)]
+#[diesel(table_name = recovery_codes)]
+pub struct RecoveryCode {
+    pub id: i32,
+    pub user_id: i32,
+    // SHA-256 of the code as shown to the user, without the dash
+    pub code_digest: Vec<u8>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = recovery_codes)]
+pub struct NewRecoveryCode {
+    pub user_id: i32,
+    pub code_digest: Vec<u8>,
+}
+
+#[derive(Queryable, Clone)]
+#[diesel(table_name = login_challenges)]
+pub struct LoginChallenge {
+    pub id: i32,
+    // SHA-256 of the token in the login challenge cookie
+    pub token_digest: Vec<u8>,
+    pub user_id: i32,
+    pub expires_on: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = login_challenges)]
+pub struct NewLoginChallenge {
+    pub token_digest: Vec<u8>,
+    pub user_id: i32,
+    pub expires_on: NaiveDateTime,
+}
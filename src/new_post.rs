@@ -1,9 +1,10 @@
-use rocket::{form::Form, get, http::CookieJar, post, FromForm, State};
+use rocket::{get, http::CookieJar, post, FromForm, State};
 use rocket_dyn_templates::Template;
 use serde::{self, Serialize};
 
 use crate::{
     application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    csrf::{CsrfForm, CsrfProtected},
     models::NewPost,
 };
 
@@ -61,11 +62,18 @@ pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, Erro
 #[derive(FromForm)]
 #[allow(clippy::module_name_repetitions)]
 pub struct NewPostForm {
+    pub csrf_token: String,
     pub title: String,
     pub description: String,
     pub content: String,
 }
 
+impl CsrfProtected for NewPostForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
 impl NewPostForm {
     pub fn all_fields_populated(&self) -> bool {
         !self.title.is_empty() && !self.description.is_empty() && !self.content.is_empty()
@@ -77,18 +85,18 @@ impl NewPostForm {
 pub fn post(
     jar: &CookieJar,
     state: &State<SharedState>,
-    data: Form<NewPostForm>,
+    data: CsrfForm<NewPostForm>,
 ) -> Result<Template, ErrorResponder> {
     if let Some(error_message) = 'requirements: {
         if !data.all_fields_populated() {
             break 'requirements Some("All fields are required!");
         }
 
-        let Some(user_info) = state.lock().unwrap().get_valid_user_info(jar)? else {
+        let Some(user_info) = state.get_valid_user_info(jar)? else {
             break 'requirements Some("You need to log in first!")
         };
 
-        state.lock().unwrap().database().create_post(NewPost {
+        state.database().create_post(NewPost {
             author: user_info.id,
             created_on: chrono::offset::Utc::now().naive_utc(),
             // HTML in title & description is automatically escaped by handebars
@@ -0,0 +1,192 @@
+use std::sync::OnceLock;
+
+use argon2::{
+    password_hash::{self, PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use serde::Deserialize;
+
+use crate::{
+    application::{generate_key, Error},
+    config::Config,
+};
+
+// what new password hashes are made with
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordAlgorithm {
+    Argon2id,
+    // only for going back to how hashes were made before
+    Bcrypt,
+}
+
+// One way of hashing passwords. Hashes are in the PHC string format, which
+// starts with the name of the algorithm, so hashes from all schemes can be
+// kept in the same column and told apart.
+trait PasswordScheme: Send + Sync {
+    // whether the hash was made by this scheme, with whatever parameters
+    fn recognizes(&self, hash: &str) -> bool;
+    fn hash(&self, password: &str) -> Result<String, Error>;
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, Error>;
+    // whether the hash was made with weaker or just different parameters than
+    // the scheme would use now
+    fn is_outdated(&self, hash: &str) -> bool;
+}
+
+struct Argon2idScheme {
+    params: Params,
+}
+
+impl Argon2idScheme {
+    fn hasher(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+impl PasswordScheme for Argon2idScheme {
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$argon2")
+    }
+
+    fn hash(&self, password: &str) -> Result<String, Error> {
+        let salt = SaltString::b64_encode(&generate_key()?[..16])
+            .map_err(|error| Error::PasswordHash(error.to_string()))?;
+        let hash = self
+            .hasher()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|error| Error::PasswordHash(error.to_string()))?;
+        Ok(hash.to_string())
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, Error> {
+        let hash =
+            PasswordHash::new(hash).map_err(|error| Error::PasswordHash(error.to_string()))?;
+        // the parameters are taken from the hash, not from self.params
+        match self.hasher().verify_password(password.as_bytes(), &hash) {
+            Ok(()) => Ok(true),
+            Err(password_hash::Error::Password) => Ok(false),
+            Err(error) => Err(Error::PasswordHash(error.to_string())),
+        }
+    }
+
+    fn is_outdated(&self, hash: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(hash) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&hash) else {
+            return true;
+        };
+
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+            || params.m_cost() != self.params.m_cost()
+            || params.t_cost() != self.params.t_cost()
+            || params.p_cost() != self.params.p_cost()
+    }
+}
+
+struct BcryptScheme {
+    cost: u32,
+}
+
+impl PasswordScheme for BcryptScheme {
+    fn recognizes(&self, hash: &str) -> bool {
+        ["$2a$", "$2b$", "$2x$", "$2y$"]
+            .iter()
+            .any(|prefix| hash.starts_with(prefix))
+    }
+
+    fn hash(&self, password: &str) -> Result<String, Error> {
+        bcrypt::hash(password, self.cost).map_err(Error::Bcrypt)
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, Error> {
+        bcrypt::verify(password, hash).map_err(Error::Bcrypt)
+    }
+
+    // the cost comes right after the prefix, e.g. $2b$12$
+    fn is_outdated(&self, hash: &str) -> bool {
+        hash.get(4..6)
+            .and_then(|cost| cost.parse::<u32>().ok())
+            .map_or(true, |cost| cost != self.cost)
+    }
+}
+
+// Hashes new passwords with the configured algorithm and still verifies
+// hashes made with any of the others. Hashes that aren't made the way new
+// ones would be are replaced on the next successful login, see needs_rehash.
+pub struct PasswordHasher {
+    algorithm: PasswordAlgorithm,
+    argon2id: Argon2idScheme,
+    bcrypt: BcryptScheme,
+    // see verify_dummy, made the first time it's needed
+    dummy_hash: OnceLock<String>,
+}
+
+impl PasswordHasher {
+    pub fn from_config(config: &Config) -> Result<PasswordHasher, Error> {
+        let params = Params::new(
+            config.argon2_memory_cost,
+            config.argon2_time_cost,
+            config.argon2_parallelism,
+            None,
+        )
+        .map_err(|error| Error::PasswordHash(format!("invalid Argon2 parameters: {error}")))?;
+
+        Ok(PasswordHasher {
+            algorithm: config.password_hash_algorithm,
+            argon2id: Argon2idScheme { params },
+            bcrypt: BcryptScheme {
+                cost: config.bcrypt_cost,
+            },
+            dummy_hash: OnceLock::new(),
+        })
+    }
+
+    fn current(&self) -> &dyn PasswordScheme {
+        match self.algorithm {
+            PasswordAlgorithm::Argon2id => &self.argon2id,
+            PasswordAlgorithm::Bcrypt => &self.bcrypt,
+        }
+    }
+
+    fn scheme_of(&self, hash: &str) -> Option<&dyn PasswordScheme> {
+        let schemes: [&dyn PasswordScheme; 2] = [&self.argon2id, &self.bcrypt];
+        schemes.into_iter().find(|scheme| scheme.recognizes(hash))
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String, Error> {
+        self.current().hash(password)
+    }
+
+    // a hash none of the schemes recognize never matches
+    pub fn verify(&self, password: &str, hash: &str) -> Result<bool, Error> {
+        match self.scheme_of(hash) {
+            Some(scheme) => scheme.verify(password, hash),
+            None => Ok(false),
+        }
+    }
+
+    // Takes as long as verifying the password against a hash made the way new
+    // ones are, for when there is no account, so that logging in as someone
+    // who doesn't exist takes as long as getting the password wrong. Accounts
+    // with hashes made any other way still take their own time until their
+    // owners log in and the hashes get replaced.
+    pub fn verify_dummy(&self, password: &str) -> Result<(), Error> {
+        let dummy_hash = if let Some(hash) = self.dummy_hash.get() {
+            hash
+        } else {
+            let hash = self.hash("dummy password")?;
+            self.dummy_hash.get_or_init(|| hash)
+        };
+        self.verify(password, dummy_hash)?;
+        Ok(())
+    }
+
+    // Whether the hash should be replaced with a new one, which can only be
+    // done while the plain password is at hand, i.e. right after verifying it.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let current = self.current();
+        !current.recognizes(hash) || current.is_outdated(hash)
+    }
+}
@@ -0,0 +1,299 @@
+use chrono::Duration;
+use rocket::{get, http::CookieJar, post, tokio::task, FromForm, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{
+        generate_key, token_digest, BaseLayoutContext, ClientInfo, Error, ErrorResponder,
+        SharedState,
+    },
+    csrf::{CsrfForm, CsrfProtected},
+    database::Database,
+    mail::{Email, Mailer},
+    models::{canonical, NewPasswordReset, PasswordReset},
+    throttle::ThrottleKey,
+    validation::validate_password,
+};
+
+#[derive(Serialize, Debug)]
+struct PasswordResetLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    // set while the form for choosing a new password should be shown
+    token: Option<String>,
+    error: Option<String>,
+    success: Option<String>,
+}
+
+impl PasswordResetLayoutContext {
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+    ) -> Result<PasswordResetLayoutContext, Error> {
+        Ok(PasswordResetLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            token: None,
+            error: None,
+            success: None,
+        })
+    }
+
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_success(mut self, success: Option<String>) -> Self {
+        self.success = success;
+        self
+    }
+}
+
+const INVALID_LINK: &str = "This link is invalid or has expired.";
+
+// Sends the owner of the address a link for choosing a new password, if the
+// address has an account. Links sent earlier stop working, so only the latest
+// e-mail is ever of any use.
+fn send_password_reset_email(
+    database: &dyn Database,
+    mailer: &dyn Mailer,
+    public_url: &str,
+    lifetime: Duration,
+    email: &str,
+) -> Result<(), Error> {
+    let Some(user) = database.get_user_by_email(email)? else {
+        return Ok(());
+    };
+    let token = generate_key()?;
+
+    database.remove_password_resets_by_user_id(user.id)?;
+    database.create_password_reset(NewPasswordReset {
+        token_digest: token_digest(&token),
+        user_id: user.id,
+        expires_on: chrono::offset::Utc::now().naive_utc() + lifetime,
+    })?;
+
+    mailer.send(&Email {
+        to: user.email.clone(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Hello {},\n\n\
+            you can choose a new password by opening the following link:\n\n\
+            {public_url}/reset_password/{}\n\n\
+            If you didn't ask for this, you can ignore this e-mail, \
+            your password stays the same.",
+            user.username,
+            hex::encode(token)
+        ),
+    })
+}
+
+// Done in the background, so that how long the response takes can't tell
+// whether the address has an account. Sending an e-mail takes a while with SMTP.
+fn send_password_reset_email_later(state: &SharedState, email: String) {
+    let database = state.database_handle();
+    let mailer = state.mailer_handle();
+    let public_url = state.config().public_url.clone();
+    let lifetime = state.config().password_reset_lifetime();
+
+    task::spawn_blocking(move || {
+        let result = send_password_reset_email(
+            database.as_ref(),
+            mailer.as_ref(),
+            &public_url,
+            lifetime,
+            &email,
+        );
+        if let Err(error) = result {
+            log::warn!("Failed to send a password reset e-mail: {error:?}");
+        }
+    });
+}
+
+// the reset the token in a link belongs to, as long as it is still usable
+fn find_password_reset(state: &SharedState, token: &str) -> Result<Option<PasswordReset>, Error> {
+    let Ok(token) = hex::decode(token) else {
+        return Ok(None);
+    };
+
+    let reset = state
+        .database()
+        .get_password_reset_by_token(token_digest(&token))?;
+
+    let now = chrono::offset::Utc::now().naive_utc();
+    Ok(reset.filter(|reset| reset.expires_on > now))
+}
+
+#[get("/forgot_password")]
+pub fn get_forgot(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "forgot_password",
+        PasswordResetLayoutContext::new(state, jar)?,
+    ))
+}
+
+#[derive(FromForm)]
+pub struct ForgotPasswordForm {
+    pub csrf_token: String,
+    pub email: String,
+}
+
+impl CsrfProtected for ForgotPasswordForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/forgot_password", data = "<data>")]
+pub fn post_forgot(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    client: ClientInfo,
+    data: CsrfForm<ForgotPasswordForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        if data.email.is_empty() {
+            break 'requirements Some("All fields are required!".to_string());
+        }
+
+        // Otherwise the form could be used to flood anyone's inbox. The
+        // address is counted whether it has an account or not, so that this
+        // doesn't tell either.
+        let now = chrono::offset::Utc::now().naive_utc();
+        let throttle = state.password_reset_throttle();
+        let keys: Vec<ThrottleKey> = client
+            .ip_address
+            .clone()
+            .map(ThrottleKey::Address)
+            .into_iter()
+            .chain([ThrottleKey::Email(canonical(&data.email))])
+            .collect();
+        if let Some(refusal) = keys.iter().find_map(|key| throttle.check(key, now)) {
+            break 'requirements Some(format!(
+                "Too many reset links were asked for, please wait {} before trying again.",
+                refusal.wait(now)
+            ));
+        }
+        for key in keys {
+            throttle.record_failure(key, now);
+        }
+
+        send_password_reset_email_later(state, data.email.clone());
+
+        None
+    } {
+        return Ok(Template::render(
+            "forgot_password",
+            PasswordResetLayoutContext::new(state, jar)?.with_error(Some(error_message)),
+        ));
+    }
+
+    // the same response either way, so that this can't be used to find out
+    // which e-mail addresses have an account
+    Ok(Template::render(
+        "forgot_password",
+        PasswordResetLayoutContext::new(state, jar)?.with_success(Some(
+            "Check your inbox, if the address belongs to an account we've sent it a reset link."
+                .to_string(),
+        )),
+    ))
+}
+
+#[get("/reset_password/<token>")]
+pub fn get_reset(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    token: &str,
+) -> Result<Template, ErrorResponder> {
+    let context = PasswordResetLayoutContext::new(state, jar)?;
+    let context = match find_password_reset(state, token)? {
+        Some(_) => context.with_token(Some(token.to_string())),
+        None => context.with_error(Some(INVALID_LINK.to_string())),
+    };
+
+    Ok(Template::render("reset_password", context))
+}
+
+#[derive(FromForm)]
+pub struct ResetPasswordForm {
+    pub csrf_token: String,
+    pub password: String,
+    pub password_repeat: String,
+}
+
+impl CsrfProtected for ResetPasswordForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/reset_password/<token>", data = "<data>")]
+pub fn post_reset(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    token: &str,
+    data: CsrfForm<ResetPasswordForm>,
+) -> Result<Template, ErrorResponder> {
+    let reset = find_password_reset(state, token)?;
+
+    if let Some(error_message) = 'requirements: {
+        let Some(reset) = &reset else {
+            break 'requirements Some(INVALID_LINK.to_string())
+        };
+        if data.password.is_empty() || data.password_repeat.is_empty() {
+            break 'requirements Some("All fields are required!".to_string());
+        }
+        if data.password != data.password_repeat {
+            break 'requirements Some("Password attempts don't match!".to_string());
+        }
+        if let Err(error) = validate_password(state.config(), &data.password) {
+            break 'requirements Some(error);
+        }
+
+        let hashed_password = state.password_hasher().hash(&data.password)?;
+
+        state
+            .database()
+            .set_user_password(reset.user_id, &hashed_password)?;
+        state
+            .database()
+            .remove_password_resets_by_user_id(reset.user_id)?;
+
+        // whoever knew the old password shouldn't stay logged in with it
+        state.database().remove_user_sessions(reset.user_id)?;
+        state.remove_session_cookie(jar);
+
+        // the link could only be opened from the user's inbox, which is all
+        // that confirming the address would prove, and any lockout is lifted
+        state.database().set_user_email_verified(reset.user_id)?;
+        state
+            .login_throttle()
+            .record_success(&ThrottleKey::Account(reset.user_id));
+
+        None
+    } {
+        // the form stays up as long as the link itself is fine
+        let token = reset.map(|_| token.to_string());
+        return Ok(Template::render(
+            "reset_password",
+            PasswordResetLayoutContext::new(state, jar)?
+                .with_token(token)
+                .with_error(Some(error_message)),
+        ));
+    }
+
+    Ok(Template::render(
+        "reset_password",
+        PasswordResetLayoutContext::new(state, jar)?.with_success(Some(
+            "Your password has been changed, you can log in now!".to_string(),
+        )),
+    ))
+}
@@ -5,39 +5,64 @@ use serde::Serialize;
 
 use crate::{
     application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
-    models::Post,
+    models::PostWithAuthor,
 };
 
+// what is shown as the author of a post, also for posts whose author deleted
+// their account but left the posts up
+pub fn author_name(post: &PostWithAuthor) -> Result<String, Error> {
+    match (post.post.author, &post.author_username) {
+        (None, _) => Ok("Deleted user".to_string()),
+        (Some(_), Some(username)) => Ok(username.clone()),
+        (Some(_), None) => Err(Error::PostHasInvalidUserId),
+    }
+}
+
+// A post that can be looked at, edited and so on. Posts in the trash only
+// show up on their author's trash page.
+pub fn visible_post(state: &SharedState, id: i32) -> Result<PostWithAuthor, Error> {
+    let post = state
+        .database()
+        .get_post_with_author_by_id(id)?
+        .ok_or(Error::InvalidPostId)?;
+    if post.post.deleted_on.is_some() {
+        return Err(Error::PostDeleted);
+    }
+    Ok(post)
+}
+
 #[derive(Serialize, Debug)]
 struct FullPostData {
+    id: i32,
     author: String,
     human_readable_creation_time: String,
+    human_readable_edit_time: Option<String>,
     title: String,
     description: String,
     content: String,
 }
 
 impl FullPostData {
-    fn from_post<'a>(state: &State<SharedState>, post: Post) -> Result<Self, Error> {
-        let author = state
-            .lock()
-            .unwrap()
-            .database()
-            .get_user_by_id(post.author)?
-            .ok_or(Error::PostHasInvalidUserId)?
-            .username;
+    fn from_post(post: PostWithAuthor) -> Result<Self, Error> {
+        let author = author_name(&post)?;
+        let post = post.post;
 
         let human_readable_creation_time =
             post.created_on.format("%d. %m. %Y. %H:%M:%S").to_string();
+        let human_readable_edit_time = post
+            .edited_on
+            .map(|edited_on| edited_on.format("%d. %m. %Y. %H:%M:%S").to_string());
 
         let content = markdown_to_html(&post.content, &ComrakOptions::default());
 
         Ok(Self {
+            id: post.id,
             author,
             title: post.title,
             description: post.description,
             content,
             human_readable_creation_time,
+            human_readable_edit_time,
         })
     }
 }
@@ -48,6 +73,11 @@ struct PostLayoutContext {
     base_context: BaseLayoutContext,
 
     post: FullPostData,
+    // whether the edit link is shown
+    can_edit: bool,
+    // authors can delete their own posts, admins anyone's that still has an
+    // author whose trash it can go to
+    can_delete: bool,
 }
 
 impl PostLayoutContext {
@@ -56,18 +86,18 @@ impl PostLayoutContext {
         jar: &CookieJar,
         id: i32,
     ) -> Result<PostLayoutContext, Error> {
-        let post = state.lock().unwrap().database().get_post_by_id(id)?;
-        let post = post
-            .map(|post| FullPostData::from_post(state, post))
-            .transpose()?;
-        if let Some(post) = post {
-            Ok(PostLayoutContext {
-                base_context: BaseLayoutContext::new(state, jar)?,
-                post,
-            })
-        } else {
-            Err(Error::InvalidPostId)
-        }
+        let post = visible_post(state, id)?;
+        let viewer = state.get_valid_user_info(jar)?;
+        let can_edit = matches!(&viewer, Some(viewer) if post.post.author == Some(viewer.id));
+        let can_delete = can_edit
+            || (post.post.author.is_some() && matches!(&viewer, Some(viewer) if viewer.is_admin));
+
+        Ok(PostLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            post: FullPostData::from_post(post)?,
+            can_edit,
+            can_delete,
+        })
     }
 }
 
@@ -0,0 +1,216 @@
+use chrono::NaiveDateTime;
+use rocket::{get, http::CookieJar, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+
+use crate::{
+    application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    models::Post,
+    post::visible_post,
+};
+
+// One state a post has been in. The earlier ones come from its revisions, the
+// last one is the post as it is now.
+struct Version {
+    created_on: NaiveDateTime,
+    title: String,
+    description: String,
+    content: String,
+}
+
+// every version of the post, oldest first
+fn versions(state: &SharedState, post: Post) -> Result<Vec<Version>, Error> {
+    let mut versions: Vec<Version> = state
+        .database()
+        .get_post_revisions(post.id)?
+        .into_iter()
+        .map(|revision| Version {
+            created_on: revision.created_on,
+            title: revision.title,
+            description: revision.description,
+            content: revision.content,
+        })
+        .collect();
+
+    versions.push(Version {
+        created_on: post.last_changed_on(),
+        title: post.title,
+        description: post.description,
+        content: post.content,
+    });
+
+    Ok(versions)
+}
+
+fn human_readable_time(time: NaiveDateTime) -> String {
+    time.format("%d. %m. %Y. %H:%M:%S").to_string()
+}
+
+#[derive(Serialize, Debug)]
+struct VersionData {
+    // counted from 1, which is how versions are referred to in the URL
+    number: usize,
+    human_readable_time: String,
+    title: String,
+    is_current: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct PostHistoryLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    id: i32,
+    title: String,
+    // newest first
+    versions: Vec<VersionData>,
+}
+
+impl PostHistoryLayoutContext {
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+        id: i32,
+    ) -> Result<PostHistoryLayoutContext, Error> {
+        let post = visible_post(state, id)?.post;
+        let title = post.title.clone();
+
+        let versions = versions(state, post)?;
+        let count = versions.len();
+        let versions = versions
+            .into_iter()
+            .enumerate()
+            .rev()
+            .map(|(index, version)| VersionData {
+                number: index + 1,
+                human_readable_time: human_readable_time(version.created_on),
+                title: version.title,
+                is_current: index + 1 == count,
+            })
+            .collect();
+
+        Ok(PostHistoryLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            id,
+            title,
+            versions,
+        })
+    }
+}
+
+#[get("/post/<id>/history")]
+pub fn get(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    id: i32,
+) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "post_history",
+        PostHistoryLayoutContext::new(state, jar, id)?,
+    ))
+}
+
+#[derive(Serialize, Debug)]
+struct DiffLine {
+    // added, removed or unchanged, used as part of the CSS class
+    kind: &'static str,
+    text: String,
+}
+
+#[derive(Serialize, Debug)]
+struct FieldDiff {
+    name: &'static str,
+    lines: Vec<DiffLine>,
+}
+
+// Compares line by line. The lines are split off beforehand so that line
+// endings, which differ between browsers, don't show up as changes.
+fn diff_field(name: &'static str, old: &str, new: &str) -> FieldDiff {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let lines = TextDiff::from_slices(&old_lines, &new_lines)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            kind: match change.tag() {
+                ChangeTag::Insert => "added",
+                ChangeTag::Delete => "removed",
+                ChangeTag::Equal => "unchanged",
+            },
+            text: change.value().to_string(),
+        })
+        .collect();
+
+    FieldDiff { name, lines }
+}
+
+#[derive(Serialize, Debug)]
+struct PostDiffLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    id: i32,
+    from: usize,
+    to: usize,
+    from_time: String,
+    to_time: String,
+    fields: Vec<FieldDiff>,
+    error: Option<String>,
+}
+
+impl PostDiffLayoutContext {
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+        id: i32,
+        from: usize,
+        to: usize,
+    ) -> Result<PostDiffLayoutContext, Error> {
+        let post = visible_post(state, id)?.post;
+        let versions = versions(state, post)?;
+
+        let mut context = PostDiffLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            id,
+            from,
+            to,
+            from_time: String::default(),
+            to_time: String::default(),
+            fields: Vec::new(),
+            error: None,
+        };
+
+        let (Some(old), Some(new)) = (
+            from.checked_sub(1).and_then(|index| versions.get(index)),
+            to.checked_sub(1).and_then(|index| versions.get(index)),
+        ) else {
+            context.error = Some("No such version of this post!".to_string());
+            return Ok(context);
+        };
+
+        context.from_time = human_readable_time(old.created_on);
+        context.to_time = human_readable_time(new.created_on);
+        context.fields = vec![
+            diff_field("Title", &old.title, &new.title),
+            diff_field("Description", &old.description, &new.description),
+            diff_field("Content", &old.content, &new.content),
+        ];
+
+        Ok(context)
+    }
+}
+
+// Shows what changed between two versions, with the numbers from the history
+// page. The content is compared as the Markdown it was written in.
+#[get("/post/<id>/diff?<from>&<to>")]
+pub fn get_diff(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    id: i32,
+    from: usize,
+    to: usize,
+) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "post_diff",
+        PostDiffLayoutContext::new(state, jar, id, from, to)?,
+    ))
+}
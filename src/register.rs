@@ -1,18 +1,40 @@
-use rocket::{form::Form, get, http::CookieJar, post, FromForm, State};
+use rocket::{get, http::CookieJar, post, FromForm, State};
 use rocket_dyn_templates::Template;
 use serde::Serialize;
 
 use crate::{
     application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
-    models::NewUser,
+    csrf::{CsrfForm, CsrfProtected},
+    models::{NewUser, User},
+    validation::{validate_email, validate_password, validate_username},
+    verify::{send_already_registered_email, send_username_taken_email, send_verification_email},
 };
 
+// shown next to the inputs they are about
+#[derive(Serialize, Debug, Default)]
+struct FieldErrors {
+    #[serde(rename = "username_error")]
+    username: Option<String>,
+    #[serde(rename = "email_error")]
+    email: Option<String>,
+    #[serde(rename = "password_error")]
+    password: Option<String>,
+}
+
+impl FieldErrors {
+    fn is_empty(&self) -> bool {
+        self.username.is_none() && self.email.is_none() && self.password.is_none()
+    }
+}
+
 #[derive(Serialize, Debug)]
 struct RegisterLayoutContext {
     #[serde(flatten)]
     base_context: BaseLayoutContext,
     previous_username: Option<String>,
     previous_email: Option<String>,
+    #[serde(flatten)]
+    field_errors: FieldErrors,
     error: Option<String>,
     success: Option<String>,
 }
@@ -26,6 +48,7 @@ impl RegisterLayoutContext {
             base_context: BaseLayoutContext::new(state, jar)?,
             previous_username: None,
             previous_email: None,
+            field_errors: FieldErrors::default(),
             error: None,
             success: None,
         })
@@ -41,6 +64,11 @@ impl RegisterLayoutContext {
         self
     }
 
+    fn with_field_errors(mut self, field_errors: FieldErrors) -> Self {
+        self.field_errors = field_errors;
+        self
+    }
+
     pub fn with_error(mut self, error: Option<String>) -> Self {
         self.error = error;
         self
@@ -63,12 +91,19 @@ pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, Erro
 #[derive(FromForm)]
 #[allow(clippy::module_name_repetitions)]
 pub struct RegisterForm {
+    pub csrf_token: String,
     pub username: String,
     pub email: String,
     pub password: String,
     pub password_repeat: String,
 }
 
+impl CsrfProtected for RegisterForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
 impl RegisterForm {
     pub fn all_fields_populated(&self) -> bool {
         !self.username.is_empty()
@@ -81,23 +116,20 @@ impl RegisterForm {
         self.password == self.password_repeat
     }
 
-    pub fn username_is_free(&self, state: &State<SharedState>) -> Result<bool, Error> {
-        Ok(state
-            .lock()
-            .unwrap()
-            .database()
-            .get_user_by_username(&self.username)?
-            .is_none())
+    fn field_errors(&self, state: &SharedState) -> FieldErrors {
+        FieldErrors {
+            username: validate_username(state.config(), &self.username).err(),
+            email: validate_email(&self.email).err(),
+            password: validate_password(state.config(), &self.password).err(),
+        }
     }
+}
 
-    pub fn email_is_free(&self, state: &State<SharedState>) -> Result<bool, Error> {
-        Ok(state
-            .lock()
-            .unwrap()
-            .database()
-            .get_user_by_email(&self.email)?
-            .is_none())
-    }
+// The account holding a username, if any. Registering and renaming go by the
+// same rule: a name is taken as long as any account holds it, confirmed or
+// not, and regardless of case or how its Unicode is composed.
+pub fn username_holder(state: &SharedState, username: &str) -> Result<Option<User>, Error> {
+    state.database().get_user_by_username(username)
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -105,9 +137,11 @@ impl RegisterForm {
 pub fn post(
     state: &State<SharedState>,
     jar: &CookieJar,
-    data: Form<RegisterForm>,
+    data: CsrfForm<RegisterForm>,
 ) -> Result<Template, ErrorResponder> {
     let context = RegisterLayoutContext::new(state, jar)?;
+    let field_errors = data.field_errors(state);
+
     if let Some(error_message) = 'requirements: {
         if !data.all_fields_populated() {
             break 'requirements Some("All fields are required!");
@@ -115,36 +149,72 @@ pub fn post(
         if !data.both_passwords_same() {
             break 'requirements Some("Password attempts don't match!");
         }
-        if !data.username_is_free(state)? {
+        if !field_errors.is_empty() {
+            break 'requirements Some("Please correct the marked fields!");
+        }
+        // the names of confirmed accounts are shown on their posts anyway, so
+        // there is nothing to hide by not saying that one is taken
+        let name_holder = username_holder(state, &data.username)?;
+        if name_holder.as_ref().is_some_and(|user| user.email_verified) {
             break 'requirements Some("Username already in use!");
         }
-        if !data.email_is_free(state)? {
-            break 'requirements Some("E-mail already in use!");
+
+        // hashed even if the account won't be created, so that the response
+        // for a taken e-mail address doesn't come back noticeably faster
+        let hashed_password = state.password_hasher().hash(&data.password)?;
+
+        // Whether an e-mail address belongs to someone is none of the
+        // visitor's business, so a taken one gets the same response as a new
+        // registration, and only the owner of the address is told about it.
+        if let Some(owner) = state.database().get_user_by_email(&data.email)? {
+            send_already_registered_email(state, &owner)?;
+            break 'requirements None;
+        }
+
+        // An unconfirmed account keeps its name, but saying so would tell
+        // whoever registered it first whether that attempt created an account,
+        // i.e. whether its e-mail address was free. So this gets the same
+        // response as well, and only the holder is told about it.
+        if let Some(holder) = name_holder {
+            send_username_taken_email(state, &holder)?;
+            break 'requirements None;
+        }
+
+        let new_user = NewUser::new(&data.username, &hashed_password, &data.email);
+        match state.database().create_user(new_user) {
+            Ok(()) => {}
+            // another registration took the name or the address in the meantime
+            Err(error) if error.is_unique_violation() => {
+                if let Some(owner) = state.database().get_user_by_email(&data.email)? {
+                    send_already_registered_email(state, &owner)?;
+                    break 'requirements None;
+                }
+                break 'requirements Some("Username already in use!");
+            }
+            Err(error) => return Err(error.into()),
+        }
+
+        if let Some(user) = state.database().get_user_by_username(&data.username)? {
+            send_verification_email(state, &user)?;
         }
+
         None
     } {
         return Ok(Template::render(
             "register",
             context
                 .with_error(Some(error_message.to_string()))
+                .with_field_errors(field_errors)
                 .with_previous_username(Some(data.username.clone()))
                 .with_previous_email(Some(data.email.clone())),
         ));
     }
 
-    let hashed_password =
-        bcrypt::hash(data.password.clone(), bcrypt::DEFAULT_COST).map_err(Error::Bcrypt)?;
-
-    let new_user = NewUser {
-        username: &data.username,
-        email: &data.email,
-        password: &hashed_password,
-    };
-
-    state.lock().unwrap().database().create_user(new_user)?;
-
     Ok(Template::render(
         "register",
-        context.with_success(Some("Account successfully registered!".to_string())),
+        context.with_success(Some(
+            "Almost done! Check your inbox for the e-mail confirming your registration."
+                .to_string(),
+        )),
     ))
 }
@@ -1,20 +1,86 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    email_verifications (id) {
+        id -> Integer,
+        token_digest -> Binary,
+        user_id -> Integer,
+        expires_on -> Timestamp,
+        new_email -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    lockouts (id) {
+        id -> Integer,
+        user_id -> Nullable<Integer>,
+        ip_address -> Nullable<Varchar>,
+        failed_attempts -> Integer,
+        locked_on -> Timestamp,
+        locked_until -> Timestamp,
+    }
+}
+
+diesel::table! {
+    login_challenges (id) {
+        id -> Integer,
+        token_digest -> Binary,
+        user_id -> Integer,
+        expires_on -> Timestamp,
+    }
+}
+
+diesel::table! {
+    password_resets (id) {
+        id -> Integer,
+        token_digest -> Binary,
+        user_id -> Integer,
+        expires_on -> Timestamp,
+    }
+}
+
+diesel::table! {
+    post_revisions (id) {
+        id -> Integer,
+        post_id -> Integer,
+        created_on -> Timestamp,
+        title -> Text,
+        description -> Text,
+        content -> Text,
+    }
+}
+
 diesel::table! {
     posts (id) {
         id -> Integer,
-        author -> Integer,
-        created_on -> Datetime,
+        author -> Nullable<Integer>,
+        created_on -> Timestamp,
         title -> Text,
         description -> Text,
-        content -> Mediumtext,
+        content -> Text,
+        edited_on -> Nullable<Timestamp>,
+        deleted_on -> Nullable<Timestamp>,
     }
 }
 
 diesel::table! {
-    sessions (session_key) {
-        session_key -> Binary,
+    recovery_codes (id) {
+        id -> Integer,
+        user_id -> Integer,
+        code_digest -> Binary,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Integer,
+        key_digest -> Binary,
         user_id -> Integer,
+        created_on -> Timestamp,
+        last_seen -> Timestamp,
+        expires_on -> Timestamp,
+        user_agent -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
     }
 }
 
@@ -24,13 +90,32 @@ diesel::table! {
         username -> Varchar,
         password -> Varchar,
         email -> Varchar,
+        is_admin -> Bool,
+        email_verified -> Bool,
+        username_canonical -> Varchar,
+        email_canonical -> Varchar,
+        totp_secret -> Nullable<Binary>,
+        totp_enabled -> Bool,
+        totp_last_step -> Nullable<BigInt>,
     }
 }
 
+diesel::joinable!(email_verifications -> users (user_id));
+diesel::joinable!(lockouts -> users (user_id));
+diesel::joinable!(login_challenges -> users (user_id));
+diesel::joinable!(password_resets -> users (user_id));
+diesel::joinable!(post_revisions -> posts (post_id));
 diesel::joinable!(posts -> users (author));
+diesel::joinable!(recovery_codes -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    email_verifications,
+    lockouts,
+    login_challenges,
+    password_resets,
+    post_revisions,
     posts,
+    recovery_codes,
     sessions,
     users,
 );
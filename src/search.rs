@@ -0,0 +1,282 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rocket::{get, http::CookieJar, FromForm, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    index::{format_cursor, parse_cursor},
+    models::{PostSearch, PostWithAuthor},
+    post::author_name,
+};
+
+// more than this many words are ignored, each one makes the query slower
+const MAX_WORDS: usize = 10;
+// how much of the content is shown with each result, in characters
+const SNIPPET_LENGTH: usize = 200;
+// how much of that comes before the first match
+const SNIPPET_LEAD: usize = 40;
+
+// Everything but letters and digits separates words, which also keeps LIKE
+// wildcards and the operators of MySQL's boolean mode out of the search.
+fn search_words(query: &str) -> Vec<String> {
+    query
+        .split(|character: char| !character.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .take(MAX_WORDS)
+        .collect()
+}
+
+// dates are entered as e.g. 2023-03-20, an empty field means no limit
+fn parse_date(value: &str) -> Result<Option<NaiveDate>, chrono::ParseError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map(Some)
+}
+
+fn start_of(date: NaiveDate) -> Option<NaiveDateTime> {
+    date.and_hms_opt(0, 0, 0)
+}
+
+// the length in bytes of the match of the lowercase word at the start of the
+// text, if there is one
+fn match_length(text: &str, word: &str) -> Option<usize> {
+    let mut remaining = word.chars();
+    for (index, character) in text.char_indices() {
+        for lowercase in character.to_lowercase() {
+            if remaining.next() != Some(lowercase) {
+                return None;
+            }
+        }
+        if remaining.as_str().is_empty() {
+            return Some(index + character.len_utf8());
+        }
+    }
+    None
+}
+
+// A piece of text that the template wraps in <mark> when it's highlighted.
+// Keeping the parts apart lets handlebars escape them as usual.
+#[derive(Serialize, Debug)]
+struct TextPart {
+    text: String,
+    highlighted: bool,
+}
+
+fn highlight(text: &str, words: &[String]) -> Vec<TextPart> {
+    let mut parts = Vec::new();
+    let mut plain_start = 0;
+    let mut index = 0;
+
+    while index < text.len() {
+        let longest_match = words
+            .iter()
+            .filter_map(|word| match_length(&text[index..], word))
+            .max();
+        let Some(length) = longest_match else {
+            index += text[index..].chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+
+        if plain_start < index {
+            parts.push(TextPart {
+                text: text[plain_start..index].to_string(),
+                highlighted: false,
+            });
+        }
+        parts.push(TextPart {
+            text: text[index..index + length].to_string(),
+            highlighted: true,
+        });
+        index += length;
+        plain_start = index;
+    }
+
+    if plain_start < text.len() {
+        parts.push(TextPart {
+            text: text[plain_start..].to_string(),
+            highlighted: false,
+        });
+    }
+    parts
+}
+
+// The part of the content around the first match, or its beginning when the
+// match was in the title or description. Line breaks become spaces.
+fn snippet(content: &str, words: &[String]) -> String {
+    let first_match = content
+        .char_indices()
+        .position(|(index, _)| {
+            words
+                .iter()
+                .any(|word| match_length(&content[index..], word).is_some())
+        })
+        .unwrap_or(0);
+    let start = first_match.saturating_sub(SNIPPET_LEAD);
+
+    let mut snippet: String = content
+        .chars()
+        .skip(start)
+        .take(SNIPPET_LENGTH)
+        .map(|character| {
+            if character.is_whitespace() {
+                ' '
+            } else {
+                character
+            }
+        })
+        .collect();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if content.chars().count() > start + SNIPPET_LENGTH {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[derive(Serialize, Debug)]
+struct SearchResultData {
+    author: String,
+    human_readable_creation_time: String,
+    title: Vec<TextPart>,
+    description: Vec<TextPart>,
+    snippet: Vec<TextPart>,
+    post_url: String,
+}
+
+impl SearchResultData {
+    fn from_post(post: PostWithAuthor, words: &[String]) -> Result<Self, Error> {
+        let author = author_name(&post)?;
+        let post = post.post;
+
+        Ok(Self {
+            author,
+            human_readable_creation_time: post
+                .created_on
+                .format("%d. %m. %Y. %H:%M:%S")
+                .to_string(),
+            title: highlight(&post.title, words),
+            description: highlight(&post.description, words),
+            snippet: highlight(&snippet(&post.content, words), words),
+            post_url: format!("/post/{}", post.id),
+        })
+    }
+}
+
+#[derive(FromForm, Debug, Default)]
+pub struct SearchForm {
+    pub q: Option<String>,
+    // a username
+    pub author: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    // where the previous page ended, see format_cursor
+    pub before: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct SearchLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    // what was searched for, to fill the form in with
+    query: String,
+    author: String,
+    from: String,
+    to: String,
+    // false until anything was searched for
+    searched: bool,
+    results: Vec<SearchResultData>,
+    // set when there are more results
+    older_cursor: Option<String>,
+    error: Option<String>,
+}
+
+impl SearchLayoutContext {
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+        form: &SearchForm,
+    ) -> Result<SearchLayoutContext, Error> {
+        let field =
+            |value: &Option<String>| value.as_deref().unwrap_or_default().trim().to_string();
+        let mut context = SearchLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            query: field(&form.q),
+            author: field(&form.author),
+            from: field(&form.from),
+            to: field(&form.to),
+            searched: false,
+            results: Vec::new(),
+            older_cursor: None,
+            error: None,
+        };
+
+        let words = search_words(&context.query);
+        if words.is_empty()
+            && context.author.is_empty()
+            && context.from.is_empty()
+            && context.to.is_empty()
+        {
+            return Ok(context);
+        }
+        context.searched = true;
+
+        let author = if context.author.is_empty() {
+            None
+        } else {
+            let Some(user) = state.database().get_user_by_username(&context.author)? else {
+                context.error = Some("There is no user with that name!".to_string());
+                return Ok(context);
+            };
+            Some(user.id)
+        };
+
+        let (Ok(from), Ok(to)) = (parse_date(&context.from), parse_date(&context.to)) else {
+            context.error = Some("Dates have to be written like 2023-03-20!".to_string());
+            return Ok(context);
+        };
+        let search = PostSearch {
+            words,
+            author,
+            from: from.and_then(start_of),
+            // the whole last day is included
+            to: to.and_then(|date| date.succ_opt()).and_then(start_of),
+        };
+
+        let database = state.database();
+        let count = i64::from(state.config().posts_per_page.max(1));
+        let before = form.before.as_deref().and_then(parse_cursor);
+        let posts = database.search_posts(&search, before, count)?;
+
+        context.older_cursor = match posts.last().map(|last| last.post.cursor()) {
+            Some(last) if !database.search_posts(&search, Some(last), 1)?.is_empty() => {
+                Some(format_cursor(last))
+            }
+            _ => None,
+        };
+        context.results = posts
+            .into_iter()
+            .map(|post| SearchResultData::from_post(post, &search.words))
+            .collect::<Result<Vec<SearchResultData>, Error>>()?;
+
+        Ok(context)
+    }
+}
+
+// Posts containing every word, newest first. Searching is done with a GET
+// form so that results can be linked to and bookmarked.
+#[allow(clippy::needless_pass_by_value)]
+#[get("/search?<search..>")]
+pub fn get(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    search: SearchForm,
+) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "search",
+        SearchLayoutContext::new(state, jar, &search)?,
+    ))
+}
@@ -0,0 +1,155 @@
+use rocket::{get, http::CookieJar, post, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    csrf::{CsrfForm, CsrfTokenForm},
+    models::Session,
+};
+
+#[derive(Serialize, Debug)]
+struct SessionData {
+    id: i32,
+    human_readable_creation_time: String,
+    human_readable_last_seen: String,
+    user_agent: String,
+    ip_address: String,
+    current: bool,
+}
+
+impl SessionData {
+    fn from_session(session: Session, current_session_id: i32) -> Self {
+        Self {
+            id: session.id,
+            human_readable_creation_time: session
+                .created_on
+                .format("%d. %m. %Y. %H:%M:%S")
+                .to_string(),
+            human_readable_last_seen: session.last_seen.format("%d. %m. %Y. %H:%M:%S").to_string(),
+            user_agent: session
+                .user_agent
+                .unwrap_or_else(|| "Unknown device".to_string()),
+            ip_address: session
+                .ip_address
+                .unwrap_or_else(|| "Unknown address".to_string()),
+            current: session.id == current_session_id,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct SessionsLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    sessions: Vec<SessionData>,
+    error: Option<String>,
+    success: Option<String>,
+}
+
+impl SessionsLayoutContext {
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+    ) -> Result<SessionsLayoutContext, Error> {
+        let (sessions, error) = match state.get_valid_session(jar)? {
+            Some((current_session, user)) => (
+                state
+                    .database()
+                    .get_sessions_by_user_id(user.id)?
+                    .into_iter()
+                    .map(|session| SessionData::from_session(session, current_session.id))
+                    .collect(),
+                None,
+            ),
+            None => (Vec::new(), Some("You need to log in first!".to_string())),
+        };
+
+        Ok(SessionsLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            sessions,
+            error,
+            success: None,
+        })
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_success(mut self, success: Option<String>) -> Self {
+        self.success = success;
+        self
+    }
+}
+
+#[get("/account/sessions")]
+pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "sessions",
+        SessionsLayoutContext::new(state, jar)?,
+    ))
+}
+
+#[post("/account/sessions/<id>/revoke", data = "<_csrf>")]
+pub fn revoke(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    id: i32,
+    _csrf: CsrfForm<CsrfTokenForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some((current_session, user)) = state.get_valid_session(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        if current_session.id == id {
+            break 'requirements Some("Use the log out button to end the current session.");
+        }
+
+        state.database().remove_user_session(user.id, id)?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "sessions",
+            SessionsLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "sessions",
+        SessionsLayoutContext::new(state, jar)?.with_success(Some("Session revoked!".to_string())),
+    ))
+}
+
+#[post("/account/sessions/revoke_others", data = "<_csrf>")]
+pub fn revoke_others(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    _csrf: CsrfForm<CsrfTokenForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some((current_session, user)) = state.get_valid_session(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        state
+            .database()
+            .remove_user_sessions_except(user.id, current_session.id)?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "sessions",
+            SessionsLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "sessions",
+        SessionsLayoutContext::new(state, jar)?
+            .with_success(Some("Logged out everywhere else!".to_string())),
+    ))
+}
@@ -0,0 +1,172 @@
+use rocket::local::blocking::Client;
+
+use super::{
+    client, get_body, link_in_mail, logged_in_client, login, logout, post_form, register,
+    register_unverified, sent_mail,
+};
+
+fn change_password(client: &Client, current: &str, new: &str) -> String {
+    post_form(
+        client,
+        "/account/password",
+        &format!("current_password={current}&password={new}&password_repeat={new}"),
+    )
+    .into_string()
+    .unwrap()
+}
+
+fn change_email(client: &Client, email: &str, current_password: &str) -> String {
+    post_form(
+        client,
+        "/account/email",
+        &format!("email={email}&current_password={current_password}"),
+    )
+    .into_string()
+    .unwrap()
+}
+
+#[test]
+fn requires_login() {
+    let client = client();
+    assert!(get_body(&client, "/account").contains("You need to log in first!"));
+
+    let body = post_form(&client, "/account/username", "username=mallory")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("You need to log in first!"));
+}
+
+#[test]
+fn shows_the_current_details() {
+    let client = logged_in_client();
+    let body = get_body(&client, "/account");
+    assert!(body.contains("value=\"alice\""));
+    assert!(body.contains("value=\"alice@example.com\""));
+}
+
+#[test]
+fn changes_the_password() {
+    let client = logged_in_client();
+    let body = change_password(&client, "swordfish-2", "correct-horse");
+    assert!(body.contains("Password changed"));
+
+    logout(&client);
+    assert!(login(&client, "alice", "swordfish-2").contains("Invalid username/e-mail or password"));
+    assert!(login(&client, "alice", "correct-horse").contains("Logged in successfully!"));
+}
+
+#[test]
+fn password_change_requires_the_current_password() {
+    let client = logged_in_client();
+    let body = change_password(&client, "wrong", "correct-horse");
+    assert!(body.contains("Current password is incorrect!"));
+
+    logout(&client);
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+}
+
+#[test]
+fn password_change_logs_out_other_devices() {
+    let client = logged_in_client();
+    let other_device = client.cookies().get("session-key").cloned().unwrap();
+    login(&client, "alice", "swordfish-2");
+
+    change_password(&client, "swordfish-2", "correct-horse");
+
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+    let body = client
+        .get("/")
+        .cookie(other_device)
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(!body.contains("Good day, alice!"));
+}
+
+#[test]
+fn changes_the_email_once_confirmed() {
+    let client = logged_in_client();
+    let body = change_email(&client, "alice@example.org", "swordfish-2");
+    assert!(body.contains("Check your inbox"));
+
+    // nothing changes until the link is opened
+    let mail = sent_mail(&client).pop().unwrap();
+    assert_eq!(mail.to, "alice@example.org");
+    assert!(get_body(&client, "/account").contains("value=\"alice@example.com\""));
+
+    let link = link_in_mail(&mail, "/verify/").unwrap();
+    let body = client.get(link).dispatch().into_string().unwrap();
+    assert!(body.contains("Your e-mail address has been changed!"));
+    assert!(get_body(&client, "/account").contains("value=\"alice@example.org\""));
+
+    logout(&client);
+    assert!(login(&client, "alice@example.org", "swordfish-2").contains("Logged in successfully!"));
+}
+
+#[test]
+fn does_not_reveal_a_taken_email() {
+    let client = logged_in_client();
+    register(&client, "bob", "bob@example.com", "swordfish-3");
+    let already_sent = sent_mail(&client).len();
+
+    let body = change_email(&client, "bob@example.com", "swordfish-2");
+    assert!(body.contains("Check your inbox"));
+    assert_eq!(sent_mail(&client).len(), already_sent);
+}
+
+#[test]
+fn email_change_requires_the_current_password() {
+    let client = logged_in_client();
+    let already_sent = sent_mail(&client).len();
+
+    let body = change_email(&client, "alice@example.org", "wrong-password");
+    assert!(body.contains("Current password is incorrect!"));
+    assert_eq!(sent_mail(&client).len(), already_sent);
+}
+
+#[test]
+fn changes_the_username() {
+    let client = logged_in_client();
+    let body = post_form(&client, "/account/username", "username=alicia")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Username changed!"));
+    assert!(body.contains("Good day, alicia!"));
+
+    logout(&client);
+    assert!(login(&client, "alicia", "swordfish-2").contains("Logged in successfully!"));
+}
+
+#[test]
+fn rejects_a_taken_username() {
+    let client = logged_in_client();
+    register(&client, "bob", "bob@example.com", "swordfish-3");
+
+    let body = post_form(&client, "/account/username", "username=bob")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Username already in use!"));
+    assert!(body.contains("Good day, alice!"));
+}
+
+#[test]
+fn rejects_a_name_held_by_an_unconfirmed_account() {
+    let client = logged_in_client();
+    // the same rule as when registering, the name stays with the pending account
+    register_unverified(&client, "bob", "bob@example.com", "swordfish-3");
+
+    let body = post_form(&client, "/account/username", "username=BOB")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Username already in use!"));
+}
+
+#[test]
+fn username_case_can_change() {
+    let client = logged_in_client();
+    let body = post_form(&client, "/account/username", "username=Alice")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Username changed!"));
+    assert!(body.contains("Good day, Alice!"));
+}
@@ -0,0 +1,37 @@
+use super::{client, client_with_config, get_body, logged_in_client, login, register};
+use crate::application::SharedState;
+
+#[test]
+fn lockouts_are_hidden_from_users() {
+    let client = logged_in_client();
+    let body = get_body(&client, "/admin/lockouts");
+    assert!(body.contains("Only administrators can see this page!"));
+    assert!(!body.contains("href=\"/admin/lockouts\""));
+}
+
+#[test]
+fn lockouts_are_hidden_from_visitors() {
+    let client = client();
+    let body = get_body(&client, "/admin/lockouts");
+    assert!(body.contains("Only administrators can see this page!"));
+}
+
+#[test]
+fn lockouts_are_listed_for_administrators() {
+    let figment = rocket::Config::figment().merge(("login_attempts_before_lockout", 2));
+    let client = client_with_config(figment);
+    register(&client, "admin", "admin@example.com", "swordfish-2");
+    register(&client, "bob", "bob@example.com", "swordfish-2");
+    login(&client, "bob", "wrong");
+    login(&client, "bob", "wrong");
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    database.set_user_admin(1, true).unwrap();
+    login(&client, "admin", "swordfish-2");
+
+    let body = get_body(&client, "/admin/lockouts");
+    assert!(body.contains("href=\"/admin/lockouts\""));
+    assert!(body.contains("Account bob"));
+    assert!(body.contains("2 failed attempts"));
+    assert!(body.contains("(active)"));
+}
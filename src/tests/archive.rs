@@ -0,0 +1,70 @@
+use chrono::NaiveDate;
+use rocket::local::blocking::Client;
+
+use super::{get_body, logged_in_client};
+use crate::{application::SharedState, models::NewPost};
+
+fn create_post_on(client: &Client, title: &str, year: i32, month: u32, day: u32) {
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    database
+        .create_post(NewPost {
+            author: 1,
+            created_on: NaiveDate::from_ymd_opt(year, month, day)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            title,
+            description: "Description",
+            content: "Content",
+        })
+        .unwrap();
+}
+
+fn get_month(client: &Client, uri: &str) -> String {
+    client
+        .get(uri.to_string())
+        .dispatch()
+        .into_string()
+        .unwrap()
+}
+
+fn archived_client() -> Client {
+    let client = logged_in_client();
+    create_post_on(&client, "January post", 2023, 1, 20);
+    create_post_on(&client, "Early March post", 2023, 3, 1);
+    create_post_on(&client, "Another early March post", 2023, 3, 1);
+    create_post_on(&client, "Late March post", 2023, 3, 31);
+    client
+}
+
+#[test]
+fn lists_months_with_counts() {
+    let client = archived_client();
+    let body = get_body(&client, "/archive");
+    assert!(body.contains("href=\"/archive/2023/3\""));
+    assert!(body.contains("March 2023"));
+    assert!(body.contains("3 posts"));
+    assert!(body.contains("January 2023"));
+    assert!(body.contains("1 posts"));
+    assert!(!body.contains("February 2023"));
+}
+
+#[test]
+fn groups_a_month_by_day() {
+    let client = archived_client();
+    let body = get_month(&client, "/archive/2023/3");
+    assert!(body.contains("01. 03. 2023. (2)"));
+    assert!(body.contains("31. 03. 2023. (1)"));
+    assert!(body.contains(">Late March post</a>"));
+    assert!(!body.contains("January post"));
+    // empty months are skipped
+    assert!(body.contains("href=\"/archive/2023/1\""));
+    assert!(!body.contains("Newer month"));
+}
+
+#[test]
+fn rejects_invalid_months() {
+    let client = archived_client();
+    assert!(get_month(&client, "/archive/2023/13").contains("No such month!"));
+    assert!(get_month(&client, "/archive/2023/0").contains("No such month!"));
+}
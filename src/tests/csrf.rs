@@ -0,0 +1,51 @@
+use rocket::http::{ContentType, Status};
+
+use super::{client, csrf_token, get_body, logged_in_client};
+
+#[test]
+fn forms_carry_the_token() {
+    let client = client();
+    let body = get_body(&client, "/login");
+    let csrf_token = csrf_token(&client);
+    assert!(body.contains(&format!("name=\"csrf_token\" value=\"{csrf_token}\"")));
+}
+
+#[test]
+fn refuses_forms_without_a_token() {
+    let client = logged_in_client();
+    let response = client
+        .post("/new_post")
+        .header(ContentType::Form)
+        .body("title=Title&description=Description&content=Content")
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+    assert!(!get_body(&client, "/").contains("Title"));
+}
+
+#[test]
+fn refuses_forms_with_a_wrong_token() {
+    let client = logged_in_client();
+    let response = client
+        .post("/logout")
+        .header(ContentType::Form)
+        .body(format!("csrf_token={}", "0".repeat(64)))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+}
+
+#[test]
+fn refuses_tokens_without_the_cookie() {
+    // another site can't set the cookie, copying a token from somewhere
+    // else isn't enough
+    let csrf_token = csrf_token(&logged_in_client());
+    let other_client = client();
+    let response = other_client
+        .post("/login")
+        .header(ContentType::Form)
+        .body(format!(
+            "username_or_email=alice&password=swordfish-2&csrf_token={csrf_token}"
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+}
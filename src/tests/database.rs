@@ -0,0 +1,65 @@
+use crate::{
+    database::{Database, MemoryDatabase},
+    models::{NewPost, NewUser},
+};
+
+// the memory backend has to reject the same rows as the UNIQUE indexes do
+#[test]
+fn canonical_names_and_emails_are_unique() {
+    let database = MemoryDatabase::new();
+    database
+        .create_user(NewUser::new("alice", "hash", "alice@example.com"))
+        .unwrap();
+
+    let error = database
+        .create_user(NewUser::new("Alice", "hash", "other@example.com"))
+        .unwrap_err();
+    assert!(error.is_unique_violation());
+
+    let error = database
+        .create_user(NewUser::new("bob", "hash", "ALICE@example.com"))
+        .unwrap_err();
+    assert!(error.is_unique_violation());
+}
+
+#[test]
+fn looks_users_up_by_canonical_form() {
+    let database = MemoryDatabase::new();
+    database
+        .create_user(NewUser::new("Alice", "hash", "Alice@Example.com"))
+        .unwrap();
+
+    let user = database.get_user_by_username("aLiCe").unwrap().unwrap();
+    assert_eq!(user.username, "Alice");
+    assert_eq!(user.email, "Alice@Example.com");
+    assert!(database
+        .get_user_by_email("alice@example.com")
+        .unwrap()
+        .is_some());
+}
+
+// the memory backend has to give the same results as the LEFT JOIN
+#[test]
+fn lists_posts_with_their_authors() {
+    let database = MemoryDatabase::new();
+    database
+        .create_user(NewUser::new("alice", "hash", "alice@example.com"))
+        .unwrap();
+    database
+        .create_post(NewPost {
+            author: 1,
+            created_on: chrono::offset::Utc::now().naive_utc(),
+            title: "Title",
+            description: "Description",
+            content: "Content",
+        })
+        .unwrap();
+
+    let posts = database.get_posts_before(None, 10).unwrap();
+    assert_eq!(posts[0].author_username.as_deref(), Some("alice"));
+
+    database.remove_user(1, true).unwrap();
+    let post = database.get_post_with_author_by_id(1).unwrap().unwrap();
+    assert_eq!(post.post.author, None);
+    assert_eq!(post.author_username, None);
+}
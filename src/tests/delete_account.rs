@@ -0,0 +1,59 @@
+use rocket::local::blocking::Client;
+
+use super::{client, create_post, get_body, logged_in_client, login, post_form, register};
+
+fn delete_account(client: &Client, body: &str) -> String {
+    post_form(client, "/account/delete", body)
+        .into_string()
+        .unwrap()
+}
+
+#[test]
+fn requires_login() {
+    let client = client();
+    assert!(get_body(&client, "/account/delete").contains("You need to log in first!"));
+    assert!(delete_account(&client, "password=swordfish-2").contains("You need to log in first!"));
+}
+
+#[test]
+fn requires_the_password() {
+    let client = logged_in_client();
+    let body = delete_account(&client, "password=wrong");
+    assert!(body.contains("Password is incorrect!"));
+    assert!(body.contains("Good day, alice!"));
+}
+
+#[test]
+fn deletes_the_account() {
+    let client = logged_in_client();
+    let body = delete_account(&client, "password=swordfish-2");
+    assert!(body.contains("Your account has been deleted."));
+    assert!(!body.contains("Good day, alice!"));
+
+    assert!(login(&client, "alice", "swordfish-2").contains("Invalid username/e-mail or password"));
+
+    // the name and address are free again
+    register(&client, "alice", "alice@example.com", "swordfish-3");
+    assert!(login(&client, "alice", "swordfish-3").contains("Logged in successfully!"));
+}
+
+#[test]
+fn keeps_posts_without_an_author() {
+    let client = logged_in_client();
+    create_post(&client, "Hello", "A first post", "Some content");
+    delete_account(&client, "password=swordfish-2");
+
+    let body = get_body(&client, "/");
+    assert!(body.contains("Hello"));
+    assert!(body.contains("Deleted user"));
+    assert!(get_body(&client, "/post/1").contains("Deleted user"));
+}
+
+#[test]
+fn deletes_posts_if_asked_to() {
+    let client = logged_in_client();
+    create_post(&client, "Hello", "A first post", "Some content");
+    delete_account(&client, "password=swordfish-2&delete_posts=on");
+
+    assert!(!get_body(&client, "/").contains("Hello"));
+}
@@ -0,0 +1,99 @@
+use rocket::{http::Status, local::blocking::Client};
+
+use super::{client, create_post, get_body, logged_in_client, login, logout, post_form, register};
+use crate::application::SharedState;
+
+fn edit(client: &Client, body: &str) -> String {
+    post_form(client, "/post/1/edit", body)
+        .into_string()
+        .unwrap()
+}
+
+#[test]
+fn prefills_the_form() {
+    let client = logged_in_client();
+    create_post(&client, "Title", "Description", "Some content");
+
+    let body = get_body(&client, "/post/1/edit");
+    assert!(body.contains("value=\"Title\""));
+    assert!(body.contains("Some content"));
+}
+
+#[test]
+fn edits_a_post() {
+    let client = logged_in_client();
+    create_post(&client, "Title", "Description", "Some content");
+
+    let body = edit(
+        &client,
+        "title=New title&description=Description&content=Other content",
+    );
+    assert!(body.contains("Post updated!"));
+
+    let body = get_body(&client, "/post/1");
+    assert!(body.contains("New title"));
+    assert!(body.contains("Other content"));
+    assert!(body.contains("edited"));
+}
+
+#[test]
+fn requires_login() {
+    let client = logged_in_client();
+    create_post(&client, "Title", "Description", "Some content");
+    logout(&client);
+
+    let body = edit(
+        &client,
+        "title=New title&description=Description&content=Other",
+    );
+    assert!(body.contains("You need to log in first!"));
+    assert!(get_body(&client, "/post/1").contains("Some content"));
+}
+
+#[test]
+fn only_the_author_can_edit() {
+    let client = logged_in_client();
+    create_post(&client, "Title", "Description", "Some content");
+    assert!(get_body(&client, "/post/1").contains("/post/1/edit"));
+    logout(&client);
+
+    register(&client, "bob", "bob@example.com", "swordfish-2");
+    login(&client, "bob", "swordfish-2");
+    assert!(!get_body(&client, "/post/1").contains("/post/1/edit"));
+
+    let body = edit(
+        &client,
+        "title=Mine now&description=Description&content=Other",
+    );
+    assert!(body.contains("Only the author can edit this post!"));
+    assert!(!get_body(&client, "/post/1").contains("Mine now"));
+}
+
+#[test]
+fn rejects_empty_and_unchanged_edits() {
+    let client = logged_in_client();
+    create_post(&client, "Title", "Description", "Some content");
+
+    let body = edit(
+        &client,
+        "title=&description=Description&content=Some content",
+    );
+    assert!(body.contains("All fields are required!"));
+
+    let body = edit(
+        &client,
+        "title=Title&description=Description&content=Some content",
+    );
+    assert!(body.contains("Nothing was changed!"));
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    assert!(database.get_post_revisions(1).unwrap().is_empty());
+}
+
+#[test]
+fn rejects_an_unknown_post() {
+    let client = client();
+    let response = client.get("/post/1/edit").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert!(response.into_string().unwrap().contains("Invalid post ID"));
+}
@@ -0,0 +1,110 @@
+use rocket::{
+    http::{ContentType, Status},
+    local::blocking::Client,
+};
+
+use super::{client, create_post, logged_in_client, post_form};
+
+// the names and contents of the files in the downloaded tar archive
+fn export(client: &Client) -> Vec<(String, String)> {
+    let response = client.get("/account/export").dispatch();
+    assert_eq!(
+        response.content_type(),
+        Some(ContentType::new("application", "x-tar"))
+    );
+    assert!(response
+        .headers()
+        .get_one("Content-Disposition")
+        .unwrap()
+        .starts_with("attachment"));
+
+    let archive = response.into_bytes().unwrap();
+    let mut files = Vec::new();
+    let mut rest = &archive[..];
+    while rest[0] != 0 {
+        let (header, after_header) = rest.split_at(512);
+        let name = String::from_utf8(
+            header[..100]
+                .split(|&byte| byte == 0)
+                .next()
+                .unwrap()
+                .to_vec(),
+        )
+        .unwrap();
+        let size = std::str::from_utf8(&header[124..135]).unwrap();
+        let size = usize::from_str_radix(size, 8).unwrap();
+        let contents = String::from_utf8(after_header[..size].to_vec()).unwrap();
+        files.push((name, contents));
+        rest = &after_header[size.div_ceil(512) * 512..];
+    }
+    files
+}
+
+fn account_json(files: &[(String, String)]) -> serde_json::Value {
+    assert_eq!(files[0].0, "account.json");
+    serde_json::from_str(&files[0].1).unwrap()
+}
+
+#[test]
+fn requires_login() {
+    let client = client();
+    let response = client.get("/account/export").dispatch();
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(response.headers().get_one("Location"), Some("/account"));
+}
+
+#[test]
+fn downloads_the_account_data() {
+    let client = logged_in_client();
+    create_post(&client, "Hello", "A first post", "# Some *markdown*");
+
+    let files = export(&client);
+    let export = account_json(&files);
+    assert_eq!(export["profile"]["username"], "alice");
+    assert_eq!(export["profile"]["email"], "alice@example.com");
+    assert_eq!(export["sessions"].as_array().unwrap().len(), 1);
+    assert_eq!(export["posts"][0]["title"], "Hello");
+    assert_eq!(export["posts"][0]["content"], "# Some *markdown*");
+    assert_eq!(
+        files[1],
+        ("posts/1.md".to_string(), "# Some *markdown*".to_string())
+    );
+}
+
+#[test]
+fn includes_the_revisions_of_every_post() {
+    let client = logged_in_client();
+    create_post(&client, "First", "Description", "one");
+    create_post(&client, "Second", "Description", "two");
+    // edited in turns, the revisions still come out by post
+    for (id, title) in [
+        (1, "First edited"),
+        (2, "Second edited"),
+        (1, "First again"),
+    ] {
+        post_form(
+            &client,
+            &format!("/post/{id}/edit"),
+            &format!("title={title}&description=Description&content=edited"),
+        );
+    }
+
+    let files = export(&client);
+    let revisions = account_json(&files)["post_revisions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|revision| revision["title"].as_str().unwrap().to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(revisions, ["First", "First edited", "Second"]);
+    assert_eq!(files[1].1, "edited");
+    assert_eq!(files.len(), 3);
+}
+
+#[test]
+fn leaves_out_secrets() {
+    let client = logged_in_client();
+    let files = export(&client);
+    assert!(!files[0].1.contains("password"));
+    assert!(!files[0].1.contains("key_digest"));
+}
@@ -0,0 +1,83 @@
+use rocket::{http::Status, local::blocking::Client};
+
+use super::{client, client_with_config, create_post, get_body, logged_in_client, login, register};
+
+// the page behind the first link to `prefix`, e.g. "/?before="
+fn follow(client: &Client, body: &str, prefix: &str) -> String {
+    let start = body.find(&format!("href=\"{prefix}")).unwrap() + "href=\"".len();
+    let link = body[start..].split('"').next().unwrap();
+    client
+        .get(link.to_string())
+        .dispatch()
+        .into_string()
+        .unwrap()
+}
+
+#[test]
+fn index_renders_without_posts() {
+    let client = client();
+    let response = client.get("/").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.into_string().unwrap().contains("Login"));
+}
+
+#[test]
+fn lists_posts_with_their_authors() {
+    let client = logged_in_client();
+    create_post(&client, "Title", "Description", "Content");
+
+    let body = get_body(&client, "/");
+    assert!(body.contains("href=\"/post/1\""));
+    assert!(body.contains("Description"));
+    assert!(body.contains("alice"));
+}
+
+#[test]
+fn lists_only_the_latest_ten_posts() {
+    let client = logged_in_client();
+    for number in 1..=11 {
+        create_post(&client, &format!("Post {number}"), "Description", "Content");
+    }
+
+    let body = get_body(&client, "/");
+    assert!(body.contains(">Post 11</a>"));
+    assert!(body.contains(">Post 2</a>"));
+    assert!(!body.contains(">Post 1</a>"));
+}
+
+#[test]
+fn pages_through_older_and_newer_posts() {
+    let client = client_with_config(rocket::Config::figment().merge(("posts_per_page", 2)));
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    login(&client, "alice", "swordfish-2");
+    for number in 1..=5 {
+        create_post(&client, &format!("Post {number}"), "Description", "Content");
+    }
+
+    let first_page = get_body(&client, "/");
+    assert!(first_page.contains(">Post 5</a>"));
+    assert!(first_page.contains(">Post 4</a>"));
+    assert!(!first_page.contains(">Post 3</a>"));
+    assert!(!first_page.contains("Newer posts"));
+
+    let second_page = follow(&client, &first_page, "/?before=");
+    assert!(second_page.contains(">Post 3</a>"));
+    assert!(second_page.contains(">Post 2</a>"));
+    assert!(!second_page.contains(">Post 4</a>"));
+
+    let last_page = follow(&client, &second_page, "/?before=");
+    assert!(last_page.contains(">Post 1</a>"));
+    assert!(!last_page.contains("Older posts"));
+
+    let back = follow(&client, &second_page, "/?after=");
+    assert!(back.contains(">Post 5</a>"));
+    assert!(back.contains(">Post 4</a>"));
+    assert!(!back.contains(">Post 3</a>"));
+}
+
+#[test]
+fn ignores_invalid_cursors() {
+    let client = logged_in_client();
+    create_post(&client, "Title", "Description", "Content");
+    assert!(get_body(&client, "/?before=yesterday").contains(">Title</a>"));
+}
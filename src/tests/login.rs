@@ -0,0 +1,203 @@
+use rocket::{
+    http::{ContentType, Header, Status},
+    local::blocking::{Client, LocalRequest},
+};
+
+use super::{client, client_with_config, csrf_token, get_body, login, register};
+use crate::application::SharedState;
+
+fn client_with_throttling(attempts_before_delay: u32, attempts_before_lockout: u32) -> Client {
+    let figment = rocket::Config::figment()
+        .merge(("login_attempts_before_delay", attempts_before_delay))
+        .merge(("login_attempts_before_lockout", attempts_before_lockout));
+    let client = client_with_config(figment);
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    client
+}
+
+// the address the requests in the address lockout tests connect from
+const ADDRESS: &str = "192.0.2.1:8000";
+
+// the token is fetched first, the request only carries the cookies the client
+// had when it was created
+fn login_request<'c>(client: &'c Client, username: &str, password: &str) -> LocalRequest<'c> {
+    let csrf_token = csrf_token(client);
+    client
+        .post("/login")
+        .header(ContentType::Form)
+        .body(format!(
+            "username_or_email={username}&password={password}&csrf_token={csrf_token}"
+        ))
+}
+
+fn login_from_address(client: &Client, username: &str, password: &str) -> String {
+    login_request(client, username, password)
+        .remote(ADDRESS.parse().unwrap())
+        .dispatch()
+        .into_string()
+        .unwrap()
+}
+
+#[test]
+fn login_page_renders() {
+    let client = client();
+    assert_eq!(client.get("/login").dispatch().status(), Status::Ok);
+}
+
+#[test]
+fn logs_in_with_username() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    let body = login(&client, "alice", "swordfish-2");
+    assert!(body.contains("Logged in successfully!"));
+    assert!(client.cookies().get("session-key").is_some());
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+}
+
+#[test]
+fn usernames_and_emails_ignore_case() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    assert!(login(&client, "ALICE", "swordfish-2").contains("Logged in successfully!"));
+    assert!(login(&client, "Alice@Example.COM", "swordfish-2").contains("Logged in successfully!"));
+}
+
+#[test]
+fn logs_in_with_email() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    let body = login(&client, "alice@example.com", "swordfish-2");
+    assert!(body.contains("Logged in successfully!"));
+}
+
+#[test]
+fn requires_all_fields() {
+    let client = client();
+    let body = login(&client, "alice", "");
+    assert!(body.contains("All fields are required!"));
+}
+
+#[test]
+fn rejects_an_unknown_user() {
+    let client = client();
+    let body = login(&client, "nobody", "swordfish-2");
+    assert!(body.contains("Invalid username/e-mail or password provided."));
+    assert!(client.cookies().get("session-key").is_none());
+}
+
+#[test]
+fn rejects_a_wrong_password() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    let body = login(&client, "alice", "swordfish-3");
+    assert!(body.contains("Invalid username/e-mail or password provided."));
+    assert!(client.cookies().get("session-key").is_none());
+}
+
+#[test]
+fn delays_attempts_after_repeated_failures() {
+    let client = client_with_throttling(2, 100);
+    login(&client, "alice", "wrong");
+    let body = login(&client, "alice", "wrong");
+    assert!(body.contains("please wait 2 seconds"));
+
+    // not even the right password gets through before the delay is over
+    let body = login(&client, "alice", "swordfish-2");
+    assert!(body.contains("please wait"));
+    assert!(client.cookies().get("session-key").is_none());
+}
+
+#[test]
+fn locks_out_after_too_many_failures() {
+    let client = client_with_throttling(100, 3);
+    login(&client, "alice", "wrong");
+    login(&client, "alice@example.com", "wrong");
+    let body = login(&client, "alice", "wrong");
+    assert!(body.contains("logging in is locked for 15 more minutes"));
+
+    let body = login(&client, "alice", "swordfish-2");
+    assert!(body.contains("logging in is locked"));
+    assert!(client.cookies().get("session-key").is_none());
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    let lockouts = database.get_latest_x_lockouts(10).unwrap();
+    assert_eq!(lockouts.len(), 1);
+    assert_eq!(lockouts[0].lockout.user_id, Some(1));
+    assert_eq!(lockouts[0].lockout.failed_attempts, 3);
+}
+
+#[test]
+fn unknown_accounts_are_throttled_too() {
+    let client = client_with_throttling(1, 100);
+    let body = login(&client, "nobody", "wrong");
+    assert!(body.contains("please wait"));
+}
+
+#[test]
+fn successful_login_forgets_failures() {
+    let client = client_with_throttling(100, 2);
+    for _ in 0..3 {
+        login(&client, "alice", "wrong");
+        assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+    }
+}
+
+#[test]
+fn locks_out_addresses_trying_many_accounts() {
+    let client = client_with_throttling(100, 3);
+    register(&client, "bob", "bob@example.com", "swordfish-2");
+    login_from_address(&client, "alice", "wrong");
+    login_from_address(&client, "bob", "wrong");
+    let body = login_from_address(&client, "carol", "wrong");
+    assert!(body.contains("logging in is locked"));
+
+    let body = login_from_address(&client, "bob", "swordfish-2");
+    assert!(body.contains("logging in is locked"));
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    let lockouts = database.get_latest_x_lockouts(10).unwrap();
+    assert_eq!(lockouts.len(), 1);
+    assert_eq!(lockouts[0].lockout.user_id, None);
+    assert_eq!(lockouts[0].lockout.ip_address.as_deref(), Some("192.0.2.1"));
+}
+
+#[test]
+fn ignores_address_headers_by_default() {
+    let client = client_with_throttling(100, 3);
+    for (attempt, username) in ["alice", "bob", "carol"].iter().enumerate() {
+        let body = login_request(&client, username, "wrong")
+            .remote(ADDRESS.parse().unwrap())
+            .header(Header::new("X-Real-IP", format!("198.51.100.{attempt}")))
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert_eq!(body.contains("logging in is locked"), attempt == 2);
+    }
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    let lockouts = database.get_latest_x_lockouts(10).unwrap();
+    assert_eq!(lockouts[0].lockout.ip_address.as_deref(), Some("192.0.2.1"));
+}
+
+#[test]
+fn trusts_the_configured_address_header() {
+    let figment = rocket::Config::figment()
+        .merge(("login_attempts_before_delay", 100))
+        .merge(("login_attempts_before_lockout", 2))
+        .merge(("client_ip_header", "X-Real-IP"));
+    let client = client_with_config(figment);
+    for username in ["alice", "bob"] {
+        login_request(&client, username, "wrong")
+            .remote(ADDRESS.parse().unwrap())
+            .header(Header::new("X-Real-IP", "203.0.113.7"))
+            .dispatch();
+    }
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    let lockouts = database.get_latest_x_lockouts(10).unwrap();
+    assert_eq!(lockouts.len(), 1);
+    assert_eq!(
+        lockouts[0].lockout.ip_address.as_deref(),
+        Some("203.0.113.7")
+    );
+}
@@ -0,0 +1,51 @@
+use rocket::http::{ContentType, Status};
+
+use super::{client, csrf_token, get_body, logged_in_client, logout};
+
+#[test]
+fn logs_out() {
+    let client = logged_in_client();
+
+    let response = logout(&client);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(response.headers().get_one("Location"), Some("/"));
+
+    assert!(client.cookies().get("session-key").is_none());
+    assert!(!get_body(&client, "/").contains("Good day, alice!"));
+}
+
+#[test]
+fn invalidates_the_session() {
+    let client = logged_in_client();
+    let session_key = client.cookies().get("session-key").cloned().unwrap();
+
+    logout(&client);
+
+    // the old key must not work even if the browser kept it around
+    let body = client
+        .post("/new_post")
+        .header(ContentType::Form)
+        .cookie(session_key)
+        .body(format!(
+            "title=Title&description=Description&content=Content&csrf_token={}",
+            csrf_token(&client)
+        ))
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(body.contains("You need to log in first!"));
+}
+
+#[test]
+fn redirects_when_not_logged_in() {
+    let client = client();
+    let response = logout(&client);
+    assert_eq!(response.status(), Status::SeeOther);
+}
+
+#[test]
+fn can_not_log_out_with_a_link() {
+    let client = logged_in_client();
+    assert_eq!(client.get("/logout").dispatch().status(), Status::NotFound);
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+}
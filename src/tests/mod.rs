@@ -0,0 +1,175 @@
+mod account;
+mod admin;
+mod archive;
+mod csrf;
+mod database;
+mod delete_account;
+mod edit_post;
+mod export;
+mod index;
+mod login;
+mod logout;
+mod new_post;
+mod password;
+mod password_reset;
+mod post;
+mod post_history;
+mod register;
+mod search;
+mod session;
+mod sessions;
+#[cfg(feature = "sqlite")]
+mod sql;
+mod throttle;
+mod trash;
+mod two_factor;
+mod verify;
+
+use std::{thread, time::Duration};
+
+use rocket::{
+    figment::Figment,
+    http::ContentType,
+    local::blocking::{Client, LocalResponse},
+};
+
+use crate::{
+    build_rocket,
+    database::MemoryDatabase,
+    mail::{Email, MemoryMailer},
+};
+
+pub fn client() -> Client {
+    client_with_config(rocket::Config::figment())
+}
+
+// for tests that need different application settings than the defaults
+pub fn client_with_config(figment: Figment) -> Client {
+    let mailer = MemoryMailer::new();
+    let rocket = build_rocket(
+        figment,
+        Box::new(MemoryDatabase::new()),
+        Box::new(mailer.clone()),
+    )
+    .manage(mailer);
+    Client::tracked(rocket).expect("valid rocket instance")
+}
+
+// the token that rendered forms carry, visiting any page hands one out
+pub fn csrf_token(client: &Client) -> String {
+    if client.cookies().get("csrf-token").is_none() {
+        client.get("/").dispatch();
+    }
+    client
+        .cookies()
+        .get("csrf-token")
+        .map(|cookie| cookie.value().to_string())
+        .unwrap()
+}
+
+// submits a form the way a browser would, including the CSRF token
+pub fn post_form<'c>(client: &'c Client, uri: &'c str, body: &str) -> LocalResponse<'c> {
+    let csrf_token = csrf_token(client);
+    let body = if body.is_empty() {
+        format!("csrf_token={csrf_token}")
+    } else {
+        format!("{body}&csrf_token={csrf_token}")
+    };
+    client
+        .post(uri)
+        .header(ContentType::Form)
+        .body(body)
+        .dispatch()
+}
+
+pub fn get_body(client: &Client, uri: &'static str) -> String {
+    client.get(uri).dispatch().into_string().unwrap()
+}
+
+// every e-mail the application has sent so far, oldest first
+pub fn sent_mail(client: &Client) -> Vec<Email> {
+    client
+        .rocket()
+        .state::<MemoryMailer>()
+        .expect("mailer is managed")
+        .sent()
+}
+
+// for e-mails sent in the background, waits until at least `count` have been
+// sent so far and returns all of them
+pub fn wait_for_mail(client: &Client, count: usize) -> Vec<Email> {
+    for _ in 0..500 {
+        let sent = sent_mail(client);
+        if sent.len() >= count {
+            return sent;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("expected {count} e-mails to have been sent");
+}
+
+// the path of the first link to `prefix` in an e-mail, e.g. "/verify/"
+pub fn link_in_mail(email: &Email, prefix: &str) -> Option<String> {
+    let start = email.body.find(prefix)?;
+    let link = email.body[start..].split_whitespace().next()?;
+    Some(link.to_string())
+}
+
+// registers without confirming the e-mail address
+pub fn register_unverified(client: &Client, username: &str, email: &str, password: &str) -> String {
+    post_form(
+        client,
+        "/register",
+        &format!(
+            "username={username}&email={email}&password={password}&password_repeat={password}"
+        ),
+    )
+    .into_string()
+    .unwrap()
+}
+
+// registers and follows the verification link, if one was sent
+pub fn register(client: &Client, username: &str, email: &str, password: &str) -> String {
+    let already_sent = sent_mail(client).len();
+    let body = register_unverified(client, username, email, password);
+
+    for mail in &sent_mail(client)[already_sent..] {
+        if let Some(link) = link_in_mail(mail, "/verify/") {
+            client.get(link).dispatch();
+        }
+    }
+
+    body
+}
+
+pub fn login(client: &Client, username_or_email: &str, password: &str) -> String {
+    post_form(
+        client,
+        "/login",
+        &format!("username_or_email={username_or_email}&password={password}"),
+    )
+    .into_string()
+    .unwrap()
+}
+
+pub fn create_post(client: &Client, title: &str, description: &str, content: &str) -> String {
+    post_form(
+        client,
+        "/new_post",
+        &format!("title={title}&description={description}&content={content}"),
+    )
+    .into_string()
+    .unwrap()
+}
+
+pub fn logout(client: &Client) -> LocalResponse<'_> {
+    post_form(client, "/logout", "")
+}
+
+// registers and logs in a user that can be used to create posts
+pub fn logged_in_client() -> Client {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    login(&client, "alice", "swordfish-2");
+    client
+}
@@ -0,0 +1,33 @@
+use rocket::http::Status;
+
+use super::{client, create_post, get_body, logged_in_client};
+
+#[test]
+fn new_post_page_renders() {
+    let client = client();
+    assert_eq!(client.get("/new_post").dispatch().status(), Status::Ok);
+}
+
+#[test]
+fn creates_a_post() {
+    let client = logged_in_client();
+    let body = create_post(&client, "Title", "Description", "Content");
+    assert!(body.contains("Created a new post!"));
+    assert!(get_body(&client, "/").contains("Title"));
+}
+
+#[test]
+fn requires_login() {
+    let client = client();
+    let body = create_post(&client, "Title", "Description", "Content");
+    assert!(body.contains("You need to log in first!"));
+    assert!(!get_body(&client, "/").contains("Title"));
+}
+
+#[test]
+fn requires_all_fields() {
+    let client = logged_in_client();
+    let body = create_post(&client, "Title", "", "Content");
+    assert!(body.contains("All fields are required!"));
+    assert!(body.contains("value=\"Title\""));
+}
@@ -0,0 +1,68 @@
+use rocket::local::blocking::Client;
+
+use super::{client, client_with_config, login, register};
+use crate::{application::SharedState, config::Config, password::PasswordHasher};
+
+fn stored_hash(client: &Client) -> String {
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    database.get_user_by_id(1).unwrap().unwrap().password
+}
+
+fn set_stored_hash(client: &Client, hash: &str) {
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    database.set_user_password(1, hash).unwrap();
+}
+
+#[test]
+fn new_passwords_are_hashed_with_argon2id() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    assert!(stored_hash(&client).starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+}
+
+#[test]
+fn bcrypt_hashes_are_replaced_on_login() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    set_stored_hash(&client, &bcrypt::hash("swordfish-2", 4).unwrap());
+
+    // a wrong password leaves the hash alone
+    login(&client, "alice", "wrong");
+    assert!(stored_hash(&client).starts_with("$2b$04$"));
+
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+    assert!(stored_hash(&client).starts_with("$argon2id$"));
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+}
+
+#[test]
+fn weaker_argon2_parameters_are_replaced_on_login() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    let weaker = PasswordHasher::from_config(&Config {
+        argon2_memory_cost: 1024,
+        argon2_time_cost: 1,
+        ..Config::default()
+    })
+    .unwrap();
+    set_stored_hash(&client, &weaker.hash("swordfish-2").unwrap());
+    assert!(stored_hash(&client).contains("m=1024,t=1,p=1"));
+
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+    assert!(stored_hash(&client).contains("m=19456,t=2,p=1"));
+}
+
+#[test]
+fn bcrypt_can_still_be_configured() {
+    let figment = rocket::Config::figment()
+        .merge(("password_hash_algorithm", "bcrypt"))
+        .merge(("bcrypt_cost", 4));
+    let client = client_with_config(figment);
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    assert!(stored_hash(&client).starts_with("$2b$04$"));
+
+    // hashes are rehashed towards whatever is configured
+    set_stored_hash(&client, &bcrypt::hash("swordfish-2", 5).unwrap());
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+    assert!(stored_hash(&client).starts_with("$2b$04$"));
+}
@@ -0,0 +1,161 @@
+use rocket::local::blocking::Client;
+
+use super::{
+    client, client_with_config, get_body, link_in_mail, logged_in_client, login, post_form,
+    register, sent_mail, wait_for_mail,
+};
+
+// asks for a reset link for alice and returns it
+fn request_reset(client: &Client) -> String {
+    let already_sent = sent_mail(client).len();
+    post_form(client, "/forgot_password", "email=alice@example.com");
+    let mail = wait_for_mail(client, already_sent + 1).pop().unwrap();
+    link_in_mail(&mail, "/reset_password/").unwrap()
+}
+
+fn reset_password(client: &Client, link: &str, password: &str) -> String {
+    post_form(
+        client,
+        link,
+        &format!("password={password}&password_repeat={password}"),
+    )
+    .into_string()
+    .unwrap()
+}
+
+#[test]
+fn login_page_links_to_the_form() {
+    let client = client();
+    assert!(get_body(&client, "/login").contains("href=\"/forgot_password\""));
+    assert!(get_body(&client, "/forgot_password").contains("Send reset link"));
+}
+
+#[test]
+fn resets_the_password() {
+    let client = logged_in_client();
+    let link = request_reset(&client);
+    assert!(client
+        .get(link.as_str())
+        .dispatch()
+        .into_string()
+        .unwrap()
+        .contains("Change password"));
+
+    let body = reset_password(&client, &link, "correct-horse");
+    assert!(body.contains("Your password has been changed"));
+
+    assert!(login(&client, "alice", "swordfish-2").contains("Invalid username/e-mail or password"));
+    assert!(login(&client, "alice", "correct-horse").contains("Logged in successfully!"));
+}
+
+#[test]
+fn does_not_reveal_unknown_addresses() {
+    let client = logged_in_client();
+    let already_sent = sent_mail(&client).len();
+
+    let known = post_form(&client, "/forgot_password", "email=alice@example.com")
+        .into_string()
+        .unwrap();
+    let unknown = post_form(&client, "/forgot_password", "email=bob@example.com")
+        .into_string()
+        .unwrap();
+    assert!(known.contains("Check your inbox"));
+    assert!(unknown.contains("Check your inbox"));
+
+    // only alice got an e-mail
+    let sent = wait_for_mail(&client, already_sent + 1);
+    assert_eq!(sent.len(), already_sent + 1);
+    assert_eq!(sent[already_sent].to, "alice@example.com");
+}
+
+#[test]
+fn links_work_only_once() {
+    let client = logged_in_client();
+    let link = request_reset(&client);
+    reset_password(&client, &link, "correct-horse");
+
+    let body = reset_password(&client, &link, "another-one");
+    assert!(body.contains("This link is invalid or has expired."));
+    assert!(login(&client, "alice", "correct-horse").contains("Logged in successfully!"));
+}
+
+#[test]
+fn only_the_latest_link_works() {
+    let client = logged_in_client();
+    let first_link = request_reset(&client);
+    let second_link = request_reset(&client);
+
+    let body = reset_password(&client, &first_link, "correct-horse");
+    assert!(body.contains("This link is invalid or has expired."));
+    let body = reset_password(&client, &second_link, "correct-horse");
+    assert!(body.contains("Your password has been changed"));
+}
+
+#[test]
+fn rejects_an_invalid_link() {
+    let client = client();
+    assert!(get_body(&client, "/reset_password/not-hex").contains("This link is invalid"));
+    assert!(get_body(&client, "/reset_password/00ff").contains("This link is invalid"));
+}
+
+#[test]
+fn requires_matching_passwords() {
+    let client = logged_in_client();
+    let link = request_reset(&client);
+
+    let body = post_form(
+        &client,
+        &link,
+        "password=correct-horse&password_repeat=correct-hose",
+    )
+    .into_string()
+    .unwrap();
+    assert!(body.contains("Password attempts"));
+    // the link can still be used after a typo
+    assert!(body.contains("Change password"));
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+}
+
+#[test]
+fn logs_out_everywhere() {
+    let client = logged_in_client();
+    let session = client.cookies().get("session-key").cloned().unwrap();
+    let link = request_reset(&client);
+    reset_password(&client, &link, "correct-horse");
+
+    let body = client
+        .get("/")
+        .cookie(session)
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(!body.contains("Good day, alice!"));
+}
+
+#[test]
+fn limits_requests_per_email_address() {
+    let figment = rocket::Config::figment()
+        .merge(("password_reset_requests_before_delay", 100))
+        .merge(("password_reset_requests_before_lockout", 2));
+    let client = client_with_config(figment);
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    let already_sent = sent_mail(&client).len();
+
+    // an address without an account is limited just the same
+    for email in ["alice@example.com", "bob@example.com"] {
+        for _ in 0..2 {
+            let body = post_form(&client, "/forgot_password", &format!("email={email}"))
+                .into_string()
+                .unwrap();
+            assert!(body.contains("Check your inbox"));
+        }
+        let body = post_form(&client, "/forgot_password", &format!("email={email}"))
+            .into_string()
+            .unwrap();
+        assert!(body.contains("Too many reset links were asked for"));
+    }
+    assert_eq!(
+        wait_for_mail(&client, already_sent + 2).len(),
+        already_sent + 2
+    );
+}
@@ -0,0 +1,33 @@
+use rocket::http::Status;
+
+use super::{client, create_post, get_body, logged_in_client};
+
+#[test]
+fn shows_a_post() {
+    let client = logged_in_client();
+    create_post(&client, "Title", "Description", "Some **bold** text");
+
+    let response = client.get("/post/1").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_string().unwrap();
+    assert!(body.contains("Title"));
+    assert!(body.contains("Description"));
+    assert!(body.contains("alice"));
+    assert!(body.contains("<strong>bold</strong>"));
+}
+
+#[test]
+fn escapes_html_in_content() {
+    let client = logged_in_client();
+    create_post(&client, "Title", "Description", "<script>alert(1)</script>");
+    assert!(!get_body(&client, "/post/1").contains("<script>"));
+}
+
+#[test]
+fn rejects_an_unknown_post() {
+    let client = client();
+    let response = client.get("/post/1").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert!(response.into_string().unwrap().contains("Invalid post ID"));
+}
@@ -0,0 +1,56 @@
+use rocket::local::blocking::Client;
+
+use super::{create_post, get_body, logged_in_client, post_form};
+
+// a post with three versions, the content changing on the second line
+fn edited_post() -> Client {
+    let client = logged_in_client();
+    create_post(&client, "First title", "Description", "one%0Atwo%0Athree");
+    post_form(
+        &client,
+        "/post/1/edit",
+        "title=Second title&description=Description&content=one%0Atwo changed%0Athree",
+    );
+    post_form(
+        &client,
+        "/post/1/edit",
+        "title=Third title&description=Description&content=one%0Atwo changed%0Athree",
+    );
+    client
+}
+
+#[test]
+fn lists_every_version() {
+    let client = edited_post();
+    let body = get_body(&client, "/post/1/history");
+    assert!(body.contains("First title"));
+    assert!(body.contains("Second title"));
+    assert!(body.contains("Third title"));
+    assert_eq!(body.matches("history-version").count(), 3);
+}
+
+#[test]
+fn shows_changed_lines() {
+    let client = edited_post();
+    let body = get_body(&client, "/post/1/diff?from=1&to=2");
+    assert!(body.contains("<div class=\"diff-line diff-removed\">two</div>"));
+    assert!(body.contains("<div class=\"diff-line diff-added\">two changed</div>"));
+    assert!(body.contains("<div class=\"diff-line diff-unchanged\">one</div>"));
+    assert!(body.contains("<div class=\"diff-line diff-removed\">First title</div>"));
+}
+
+#[test]
+fn compares_any_two_versions() {
+    let client = edited_post();
+    let body = get_body(&client, "/post/1/diff?from=3&to=1");
+    assert!(body.contains("<div class=\"diff-line diff-removed\">Third title</div>"));
+    assert!(body.contains("<div class=\"diff-line diff-added\">First title</div>"));
+    assert!(body.contains("<div class=\"diff-line diff-added\">two</div>"));
+}
+
+#[test]
+fn rejects_unknown_versions() {
+    let client = edited_post();
+    assert!(get_body(&client, "/post/1/diff?from=0&to=2").contains("No such version"));
+    assert!(get_body(&client, "/post/1/diff?from=1&to=4").contains("No such version"));
+}
@@ -0,0 +1,191 @@
+use rocket::http::Status;
+
+use super::{
+    client, client_with_config, get_body, link_in_mail, login, post_form, register,
+    register_unverified, sent_mail,
+};
+
+#[test]
+fn register_page_renders() {
+    let client = client();
+    assert_eq!(client.get("/register").dispatch().status(), Status::Ok);
+}
+
+#[test]
+fn registers_a_new_account() {
+    let client = client();
+    let body = register(&client, "alice", "alice@example.com", "swordfish-2");
+    assert!(body.contains("Check your inbox"));
+}
+
+#[test]
+fn requires_all_fields() {
+    let client = client();
+    let body = register(&client, "alice", "", "swordfish-2");
+    assert!(body.contains("All fields are required!"));
+}
+
+#[test]
+fn requires_matching_passwords() {
+    let client = client();
+    let body = post_form(
+        &client,
+        "/register",
+        "username=alice&email=alice@example.com&password=swordfish-2&password_repeat=swordfish-3",
+    )
+    .into_string()
+    .unwrap();
+    assert!(body.contains("Password attempts"));
+    assert!(!body.contains("Check your inbox"));
+}
+
+#[test]
+fn rejects_a_taken_username() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    let body = register(&client, "alice", "other@example.com", "swordfish-2");
+    assert!(body.contains("Username already in use!"));
+}
+
+#[test]
+fn does_not_reveal_a_taken_email() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    let body = register(&client, "bob", "alice@example.com", "swordfish-3");
+    assert!(body.contains("Check your inbox"));
+    assert!(!body.contains("E-mail already in use!"));
+
+    // but no second account was created with it
+    assert!(login(&client, "bob", "swordfish-3").contains("Invalid username/e-mail or password"));
+    assert!(login(&client, "alice@example.com", "swordfish-2").contains("Logged in successfully!"));
+
+    // the owner of the address is told instead
+    let mail = sent_mail(&client).pop().unwrap();
+    assert_eq!(mail.to, "alice@example.com");
+    assert!(mail.body.contains("somebody tried to register"));
+    assert_eq!(link_in_mail(&mail, "/verify/"), None);
+}
+
+#[test]
+fn keeps_previous_input_on_error() {
+    let client = client();
+    let body = register(&client, "alice", "alice@example.com", "");
+    assert!(body.contains("value=\"alice\""));
+    assert!(body.contains("value=\"alice@example.com\""));
+}
+
+#[test]
+fn rejects_short_passwords() {
+    let client = client();
+    let body = register(&client, "alice", "alice@example.com", "x7");
+    assert!(body.contains("Please correct the marked fields!"));
+    assert!(body.contains("Passwords have to be at least 8 characters long."));
+    assert!(login(&client, "alice", "x7").contains("Invalid username/e-mail or password"));
+}
+
+#[test]
+fn rejects_common_passwords() {
+    let client = client();
+    let body = register(&client, "alice", "alice@example.com", "Password123");
+    assert!(body.contains("This password is too common"));
+}
+
+#[test]
+fn rejects_passwords_bcrypt_would_truncate() {
+    let client = client();
+    // 40 characters, but 80 bytes
+    let password = "\u{10d}".repeat(40);
+    let body = register(&client, "alice", "alice@example.com", &password);
+    assert!(body.contains("Passwords can be at most 72 bytes long"));
+}
+
+#[test]
+fn rejects_long_usernames() {
+    let client = client();
+    let username = "a".repeat(10_000);
+    let body = register(&client, &username, "alice@example.com", "swordfish-2");
+    assert!(body.contains("Usernames have to be between 3 and 32 characters long."));
+}
+
+#[test]
+fn rejects_symbols_in_usernames() {
+    let client = client();
+    let body = register(&client, "alice%3Cb%3E", "alice@example.com", "swordfish-2");
+    assert!(body.contains("Usernames can only contain letters, digits and these symbols: _-."));
+
+    let body = register(&client, "alice_b.c-d", "alice@example.com", "swordfish-2");
+    assert!(body.contains("Check your inbox"));
+}
+
+#[test]
+fn rejects_invalid_emails() {
+    let client = client();
+    let body = register(&client, "alice", "alice.example.com", "swordfish-2");
+    assert!(body.contains("look like an e-mail address."));
+}
+
+#[test]
+fn policy_is_configurable() {
+    let figment = rocket::Config::figment()
+        .merge(("username_min_length", 8))
+        .merge(("password_min_length", 20))
+        .merge(("username_allowed_symbols", ""));
+    let client = client_with_config(figment);
+
+    let body = register(&client, "alice_b", "alice@example.com", "swordfish-2");
+    assert!(body.contains("Usernames have to be between 8 and 32 characters long."));
+    assert!(body.contains("Passwords have to be at least 20 characters long."));
+
+    let body = register(&client, "alice_bob", "alice@example.com", "swordfish-2");
+    assert!(body.contains("Usernames can only contain letters and digits."));
+}
+
+#[test]
+fn registering_a_pending_name_notifies_its_holder() {
+    let client = client();
+    register_unverified(&client, "carol", "carol@example.com", "swordfish-3");
+    let link = link_in_mail(&sent_mail(&client).pop().unwrap(), "/verify/").unwrap();
+
+    // the same response as a new registration, but only the holder hears of it
+    let body = register_unverified(&client, "carol", "mallory@example.com", "swordfish-4");
+    assert!(body.contains("Check your inbox"));
+    let mail = sent_mail(&client).pop().unwrap();
+    assert_eq!(mail.to, "carol@example.com");
+    assert!(mail.body.contains("somebody tried to register"));
+    assert!(sent_mail(&client)
+        .iter()
+        .all(|mail| mail.to != "mallory@example.com"));
+
+    // the pending account is untouched and can still be confirmed
+    assert!(client
+        .get(link)
+        .dispatch()
+        .into_string()
+        .unwrap()
+        .contains("you can log in now"));
+    assert!(login(&client, "carol", "swordfish-4").contains("Invalid username/e-mail or password"));
+    login(&client, "carol", "swordfish-3");
+    assert!(get_body(&client, "/").contains("Good day, carol!"));
+}
+
+#[test]
+fn usernames_differing_only_in_case_are_taken() {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    let body = register(&client, "ALICE", "other@example.com", "swordfish-2");
+    assert!(body.contains("Username already in use!"));
+}
+
+#[test]
+fn emails_are_compared_in_canonical_form() {
+    let client = client();
+    // the ligature is the same as "fi" once normalized
+    register(&client, "alice", "\u{fb01}sh@example.com", "swordfish-2");
+    let body = register(&client, "bob", "FISH@Example.com", "swordfish-3");
+    assert!(body.contains("Check your inbox"));
+
+    // the owner is told instead of a second account being created
+    let mail = sent_mail(&client).pop().unwrap();
+    assert!(mail.body.contains("somebody tried to register"));
+    assert!(login(&client, "bob", "swordfish-3").contains("Invalid username/e-mail or password"));
+}
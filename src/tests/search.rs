@@ -0,0 +1,168 @@
+use chrono::NaiveDate;
+use rocket::local::blocking::Client;
+
+use super::{client_with_config, get_body, logged_in_client, login, post_form, register};
+use crate::{application::SharedState, models::NewPost};
+
+fn create_post_on(client: &Client, title: &str, content: &str, year: i32, month: u32, day: u32) {
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    database
+        .create_post(NewPost {
+            author: 1,
+            created_on: NaiveDate::from_ymd_opt(year, month, day)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            title,
+            description: "Description",
+            content,
+        })
+        .unwrap();
+}
+
+fn search(client: &Client, uri: &str) -> String {
+    client
+        .get(uri.to_string())
+        .dispatch()
+        .into_string()
+        .unwrap()
+}
+
+fn searchable_client() -> Client {
+    let client = logged_in_client();
+    create_post_on(&client, "Fruit", "I ate a Banana today.", 2023, 1, 20);
+    create_post_on(&client, "Vegetables", "Carrots and peas.", 2023, 3, 1);
+    create_post_on(&client, "Smoothie", "Banana and carrots.", 2023, 3, 31);
+    client
+}
+
+#[test]
+fn shows_only_the_form_without_a_search() {
+    let client = searchable_client();
+    let body = get_body(&client, "/search");
+    assert!(body.contains("action=\"/search\""));
+    assert!(!body.contains("Fruit"));
+    assert!(!body.contains("No posts match your search."));
+}
+
+#[test]
+fn finds_words_regardless_of_case() {
+    let client = searchable_client();
+    let body = search(&client, "/search?q=BANANA");
+    assert!(body.contains("Fruit"));
+    assert!(body.contains("Smoothie"));
+    assert!(!body.contains("Vegetables"));
+}
+
+#[test]
+fn requires_every_word() {
+    let client = searchable_client();
+    let body = search(&client, "/search?q=banana+carrots");
+    assert!(body.contains("Smoothie"));
+    assert!(!body.contains("Fruit"));
+    assert!(!body.contains("Vegetables"));
+
+    let body = search(&client, "/search?q=banana+potatoes");
+    assert!(body.contains("No posts match your search."));
+}
+
+#[test]
+fn highlights_the_matches() {
+    let client = searchable_client();
+    let body = search(&client, "/search?q=banana");
+    assert!(body.contains("I ate a <mark>Banana</mark> today."));
+}
+
+#[test]
+fn escapes_the_text_around_matches() {
+    let client = logged_in_client();
+    create_post_on(&client, "Markup", "<b>banana</b>", 2023, 1, 1);
+    let body = search(&client, "/search?q=banana");
+    assert!(body.contains("&lt;b&gt;<mark>banana</mark>&lt;/b&gt;"));
+}
+
+#[test]
+fn filters_by_author() {
+    let client = searchable_client();
+    register(&client, "bob", "bob@example.com", "swordfish-2");
+    login(&client, "bob", "swordfish-2");
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    let bob = database.get_user_by_username("bob").unwrap().unwrap();
+    database
+        .create_post(NewPost {
+            author: bob.id,
+            created_on: chrono::offset::Utc::now().naive_utc(),
+            title: "Bob's banana bread",
+            description: "Description",
+            content: "Banana",
+        })
+        .unwrap();
+
+    let body = search(&client, "/search?q=banana&author=bob");
+    assert!(body.contains("banana</mark> bread"));
+    assert!(!body.contains("Fruit"));
+
+    // the author alone is enough to search with
+    let body = search(&client, "/search?author=alice");
+    assert!(body.contains("Fruit"));
+    assert!(body.contains("Vegetables"));
+    assert!(!body.contains("bread"));
+
+    let body = search(&client, "/search?q=banana&author=nobody");
+    assert!(body.contains("There is no user with that name!"));
+}
+
+#[test]
+fn filters_by_date() {
+    let client = searchable_client();
+    let body = search(&client, "/search?q=banana&from=2023-03-01");
+    assert!(body.contains("Smoothie"));
+    assert!(!body.contains("Fruit"));
+
+    // the last day is included
+    let body = search(&client, "/search?from=2023-03-01&to=2023-03-01");
+    assert!(body.contains("Vegetables"));
+    assert!(!body.contains("Smoothie"));
+    assert!(!body.contains("Fruit"));
+
+    let body = search(&client, "/search?q=banana&to=yesterday");
+    assert!(body.contains("Dates have to be written like 2023-03-20!"));
+}
+
+#[test]
+fn leaves_out_deleted_posts() {
+    let client = searchable_client();
+    post_form(&client, "/post/1/delete", "");
+    let body = search(&client, "/search?q=banana");
+    assert!(!body.contains("Fruit"));
+    assert!(body.contains("Smoothie"));
+}
+
+#[test]
+fn pages_through_older_results() {
+    let figment = rocket::Config::figment().merge(("posts_per_page", 2));
+    let client = client_with_config(figment);
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    login(&client, "alice", "swordfish-2");
+    create_post_on(&client, "First", "Banana", 2023, 1, 1);
+    create_post_on(&client, "Second", "Banana", 2023, 1, 2);
+    create_post_on(&client, "Third", "Banana", 2023, 1, 3);
+
+    let body = search(&client, "/search?q=banana");
+    assert!(body.contains("Third"));
+    assert!(body.contains("Second"));
+    assert!(!body.contains("First"));
+    assert!(body.contains("Older results"));
+
+    let cursor = body
+        .split("name=\"before\" value=\"")
+        .nth(1)
+        .unwrap()
+        .split('"')
+        .next()
+        .unwrap();
+    let body = search(&client, &format!("/search?q=banana&before={cursor}"));
+    assert!(body.contains("First"));
+    assert!(!body.contains("Second"));
+    assert!(!body.contains("Older results"));
+}
@@ -0,0 +1,196 @@
+use chrono::{offset::Utc, Duration, NaiveDateTime};
+use rocket::http::SameSite;
+
+use super::{client_with_config, get_body, login, logout, post_form, register};
+use crate::{
+    application::{token_digest, SharedState},
+    database::{Database, MemoryDatabase},
+    models::NewSession,
+};
+
+fn client_with_session_lifetime(
+    session_lifetime: u32,
+    session_renewal_interval: u32,
+) -> rocket::local::blocking::Client {
+    let figment = rocket::Config::figment()
+        .merge(("session_lifetime", session_lifetime))
+        .merge(("session_renewal_interval", session_renewal_interval));
+    let client = client_with_config(figment);
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    login(&client, "alice", "swordfish-2");
+    client
+}
+
+#[test]
+fn session_cookie_has_max_age() {
+    let figment = rocket::Config::figment().merge(("session_lifetime", 3600));
+    let client = client_with_config(figment);
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+
+    let response = post_form(
+        &client,
+        "/login",
+        "username_or_email=alice&password=swordfish-2",
+    );
+    let cookie = response.cookies().get("session-key").cloned().unwrap();
+    assert_eq!(cookie.max_age(), Some(rocket::time::Duration::hours(1)));
+}
+
+// Moves the client's session to another point in time, as if it had been
+// seen and set to expire then, so the tests don't have to wait for it.
+fn backdate_session(
+    client: &rocket::local::blocking::Client,
+    last_seen: NaiveDateTime,
+    expires_on: NaiveDateTime,
+) -> Vec<u8> {
+    let cookie = client.cookies().get("session-key").cloned().unwrap();
+    let key_digest = token_digest(&hex::decode(cookie.value()).unwrap());
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    database
+        .renew_session(key_digest.clone(), last_seen, expires_on)
+        .unwrap();
+    key_digest
+}
+
+#[test]
+fn expired_session_is_rejected() {
+    let client = client_with_session_lifetime(3600, 60);
+    let session_key = client.cookies().get("session-key").cloned().unwrap();
+    let now = Utc::now().naive_utc();
+    backdate_session(
+        &client,
+        now - Duration::hours(2),
+        now - Duration::seconds(1),
+    );
+
+    // the browser would have dropped the cookie, but the server must not
+    // rely on that
+    let body = client
+        .get("/")
+        .cookie(session_key)
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(!body.contains("Good day, alice!"));
+}
+
+#[test]
+fn active_session_is_renewed() {
+    let client = client_with_session_lifetime(3600, 60);
+    // about to expire, and not seen for longer than the renewal interval
+    let now = Utc::now().naive_utc();
+    let key_digest = backdate_session(
+        &client,
+        now - Duration::minutes(5),
+        now + Duration::seconds(5),
+    );
+
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    let session = database.get_session_by_key(key_digest).unwrap().unwrap();
+    assert!(session.expires_on > now + Duration::minutes(59));
+    assert!(session.last_seen >= now);
+}
+
+#[test]
+fn recently_seen_session_is_not_renewed() {
+    let client = client_with_session_lifetime(3600, 60);
+    let now = Utc::now().naive_utc();
+    let expires_on = now + Duration::minutes(30);
+    let key_digest = backdate_session(&client, now - Duration::seconds(10), expires_on);
+
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    let session = database.get_session_by_key(key_digest).unwrap().unwrap();
+    assert_eq!(session.expires_on, expires_on);
+}
+
+#[test]
+fn expired_sessions_are_purged() {
+    let database = MemoryDatabase::new();
+    let now = Utc::now().naive_utc();
+    let session = |key_digest: u8, expires_on| NewSession {
+        key_digest: vec![key_digest],
+        user_id: 1,
+        created_on: now,
+        last_seen: now,
+        expires_on,
+        user_agent: None,
+        ip_address: None,
+    };
+
+    database
+        .create_session(session(1, now - Duration::seconds(1)))
+        .unwrap();
+    database
+        .create_session(session(2, now + Duration::seconds(60)))
+        .unwrap();
+
+    assert_eq!(database.remove_expired_sessions(now).unwrap(), 1);
+    assert!(database.get_session_by_key(vec![1]).unwrap().is_none());
+    assert!(database.get_session_by_key(vec![2]).unwrap().is_some());
+}
+
+#[test]
+fn only_the_session_key_digest_is_stored() {
+    let client = client_with_session_lifetime(3600, 60);
+    let cookie = client.cookies().get("session-key").cloned().unwrap();
+    let session_key = hex::decode(cookie.value()).unwrap();
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+
+    assert!(database
+        .get_session_by_key(session_key.clone())
+        .unwrap()
+        .is_none());
+    let session = database
+        .get_session_by_key(token_digest(&session_key))
+        .unwrap()
+        .unwrap();
+    assert_ne!(session.key_digest, session_key);
+}
+
+#[test]
+fn session_cookie_is_hardened() {
+    let client = client_with_session_lifetime(3600, 60);
+    let cookie = client.cookies().get("session-key").cloned().unwrap();
+    assert_eq!(cookie.http_only(), Some(true));
+    assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    assert_eq!(cookie.path(), Some("/"));
+    // the test client doesn't use TLS
+    assert_eq!(cookie.secure(), None);
+}
+
+#[test]
+fn session_cookie_settings_are_configurable() {
+    let figment = rocket::Config::figment()
+        .merge(("session_cookie_same_site", "strict"))
+        .merge(("secure_session_cookie", true));
+    let client = client_with_config(figment);
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+
+    let response = post_form(
+        &client,
+        "/login",
+        "username_or_email=alice&password=swordfish-2",
+    );
+    let cookie = response.cookies().get("session-key").cloned().unwrap();
+    assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+    assert_eq!(cookie.secure(), Some(true));
+}
+
+#[test]
+fn private_session_cookie_is_encrypted() {
+    let figment = rocket::Config::figment().merge(("private_session_cookie", true));
+    let client = client_with_config(figment);
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    login(&client, "alice", "swordfish-2");
+
+    let cookie = client.cookies().get("session-key").cloned().unwrap();
+    assert!(hex::decode(cookie.value()).is_err());
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+
+    logout(&client);
+    assert!(!get_body(&client, "/").contains("Good day, alice!"));
+}
@@ -0,0 +1,101 @@
+use rocket::{
+    http::{ContentType, Header},
+    local::blocking::Client,
+};
+
+use super::{client, csrf_token, get_body, login, post_form, register};
+
+fn login_from(client: &Client, user_agent: &'static str) {
+    client
+        .post("/login")
+        .header(ContentType::Form)
+        .header(Header::new("User-Agent", user_agent))
+        .body(format!(
+            "username_or_email=alice&password=swordfish-2&csrf_token={}",
+            csrf_token(client)
+        ))
+        .dispatch();
+}
+
+// logs in from two devices, returning the first device's session cookie
+fn client_with_two_sessions() -> (Client, rocket::http::Cookie<'static>) {
+    let client = client();
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    login_from(&client, "OldBrowser/1.0");
+    let first_session = client.cookies().get("session-key").cloned().unwrap();
+    login_from(&client, "NewBrowser/2.0");
+    (client, first_session)
+}
+
+fn is_logged_in(client: &Client, session: rocket::http::Cookie<'static>) -> bool {
+    client
+        .get("/")
+        .cookie(session)
+        .dispatch()
+        .into_string()
+        .unwrap()
+        .contains("Good day, alice!")
+}
+
+#[test]
+fn requires_login() {
+    let client = client();
+    assert!(get_body(&client, "/account/sessions").contains("You need to log in first!"));
+}
+
+#[test]
+fn lists_sessions() {
+    let (client, _) = client_with_two_sessions();
+    let body = get_body(&client, "/account/sessions");
+    assert!(body.contains("OldBrowser/1.0"));
+    assert!(body.contains("NewBrowser/2.0"));
+    assert!(body.contains("This device"));
+}
+
+#[test]
+fn revokes_a_session() {
+    let (client, first_session) = client_with_two_sessions();
+
+    let body = post_form(&client, "/account/sessions/1/revoke", "")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Session revoked!"));
+    assert!(!body.contains("OldBrowser/1.0"));
+
+    assert!(!is_logged_in(&client, first_session));
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+}
+
+#[test]
+fn does_not_revoke_the_current_session() {
+    let (client, _) = client_with_two_sessions();
+    let body = post_form(&client, "/account/sessions/2/revoke", "")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Use the log out button"));
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+}
+
+#[test]
+fn does_not_revoke_sessions_of_other_users() {
+    let (client, first_session) = client_with_two_sessions();
+
+    register(&client, "bob", "bob@example.com", "swordfish-2");
+    login(&client, "bob", "swordfish-2");
+    post_form(&client, "/account/sessions/1/revoke", "");
+
+    assert!(is_logged_in(&client, first_session));
+}
+
+#[test]
+fn logs_out_everywhere_else() {
+    let (client, first_session) = client_with_two_sessions();
+
+    let body = post_form(&client, "/account/sessions/revoke_others", "")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Logged out everywhere else!"));
+
+    assert!(!is_logged_in(&client, first_session));
+    assert!(get_body(&client, "/").contains("Good day, alice!"));
+}
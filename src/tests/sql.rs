@@ -0,0 +1,396 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{connection::SimpleConnection, sqlite::SqliteConnection, Connection};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+use crate::{
+    database::{Database, PoolSettings, SqliteDatabase},
+    models::{
+        canonical, MonthPostCount, NewLockout, NewPost, NewUser, PostCursor, PostEdit, PostSearch,
+        PostWithAuthor,
+    },
+    Error,
+};
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/sqlite");
+
+// the migration before the canonical names are made unique
+const CANONICAL_NAMES_VERSION: &str = "20230302105318";
+
+// A database file of its own for each test, SQLite's :memory: databases don't
+// outlive the connection.
+struct DatabaseFile(PathBuf);
+
+impl DatabaseFile {
+    fn new() -> DatabaseFile {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        DatabaseFile(env::temp_dir().join(format!(
+            "seminarski-test-{}-{}.sqlite",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+
+    fn open(&self) -> SqliteDatabase {
+        let pool_settings = PoolSettings {
+            size: 1,
+            timeout: Duration::from_secs(5),
+        };
+        SqliteDatabase::new(self.path(), &pool_settings).unwrap()
+    }
+}
+
+impl Drop for DatabaseFile {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).ok();
+    }
+}
+
+// a database as it was right before the canonical names were made unique,
+// with the given accounts in it
+fn database_with_accounts(accounts: &[(&str, &str)]) -> DatabaseFile {
+    let file = DatabaseFile::new();
+    let mut connection = SqliteConnection::establish(file.path()).unwrap();
+    loop {
+        let version = connection.run_next_migration(MIGRATIONS).unwrap();
+        if version.to_string() == CANONICAL_NAMES_VERSION {
+            break;
+        }
+    }
+
+    for (username, email) in accounts {
+        connection
+            .batch_execute(&format!(
+                "INSERT INTO users (username, password, email) \
+                VALUES ('{username}', 'hash', '{email}')"
+            ))
+            .unwrap();
+    }
+    file
+}
+
+// a fully migrated database with an author, user 1, for the posts
+fn database_with_author() -> (DatabaseFile, SqliteDatabase) {
+    let file = DatabaseFile::new();
+    let database = file.open();
+    database.run_pending_migrations().unwrap();
+    database
+        .create_user(NewUser {
+            username: "alice",
+            password: "hash",
+            email: "alice@example.com",
+            username_canonical: canonical("alice"),
+            email_canonical: canonical("alice@example.com"),
+        })
+        .unwrap();
+    (file, database)
+}
+
+fn time(year: i32, month: u32, day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap()
+}
+
+fn create_post(database: &SqliteDatabase, title: &str, created_on: NaiveDateTime) {
+    create_post_with_content(database, title, created_on, "Content");
+}
+
+fn create_post_with_content(
+    database: &SqliteDatabase,
+    title: &str,
+    created_on: NaiveDateTime,
+    content: &str,
+) {
+    database
+        .create_post(NewPost {
+            title,
+            author: 1,
+            created_on,
+            description: "Description",
+            content,
+        })
+        .unwrap();
+}
+
+fn titles(posts: Vec<PostWithAuthor>) -> Vec<String> {
+    posts.into_iter().map(|post| post.post.title).collect()
+}
+
+#[test]
+fn existing_accounts_get_canonical_names() {
+    let file = database_with_accounts(&[
+        ("\u{ff21}lice", "alice@example.com"),
+        ("bob", "\u{fb01}sh@Example.com"),
+    ]);
+    let database = file.open();
+    database.run_pending_migrations().unwrap();
+
+    let alice = database.get_user_by_username("alice").unwrap().unwrap();
+    assert_eq!(alice.username, "\u{ff21}lice");
+    let bob = database
+        .get_user_by_email("FISH@example.com")
+        .unwrap()
+        .unwrap();
+    assert_eq!(bob.username, "bob");
+}
+
+#[test]
+fn clashing_accounts_stop_the_migration() {
+    let file = database_with_accounts(&[
+        ("alice", "alice@example.com"),
+        ("Alice", "other@example.com"),
+    ]);
+    let database = file.open();
+
+    let Err(Error::Migration(message)) = database.run_pending_migrations() else {
+        panic!("the migration went through");
+    };
+    assert!(message.contains("users 1 and 2 have the same username"));
+    assert!(database.has_pending_migrations().unwrap());
+}
+
+#[test]
+fn counts_posts_by_month() {
+    let (_file, database) = database_with_author();
+    create_post(&database, "First", time(2022, 12, 31));
+    create_post(&database, "Second", time(2023, 2, 1));
+    create_post(&database, "Third", time(2023, 2, 28));
+    create_post(&database, "Trashed", time(2023, 3, 15));
+    database
+        .set_post_deleted_on(4, Some(time(2023, 3, 16)))
+        .unwrap();
+
+    assert_eq!(
+        database.get_post_counts_by_month().unwrap(),
+        vec![
+            MonthPostCount {
+                year: 2022,
+                month: 12,
+                count: 1
+            },
+            MonthPostCount {
+                year: 2023,
+                month: 2,
+                count: 2
+            },
+        ]
+    );
+    assert_eq!(
+        database
+            .get_latest_post_time_before(time(2023, 2, 1))
+            .unwrap(),
+        Some(time(2022, 12, 31))
+    );
+    assert_eq!(
+        database
+            .get_earliest_post_time_from(time(2023, 3, 1))
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn pages_through_posts_newest_first() {
+    let (_file, database) = database_with_author();
+    create_post(&database, "First", time(2023, 1, 1));
+    create_post(&database, "Second", time(2023, 1, 2));
+    // created in the same instant, so only the id tells them apart
+    create_post(&database, "Third", time(2023, 1, 2));
+    create_post(&database, "Fourth", time(2023, 1, 3));
+    create_post(&database, "Trashed", time(2023, 1, 4));
+    database
+        .set_post_deleted_on(5, Some(time(2023, 1, 5)))
+        .unwrap();
+
+    assert_eq!(
+        titles(database.get_posts_before(None, 2).unwrap()),
+        ["Fourth", "Third"]
+    );
+    let third = PostCursor {
+        created_on: time(2023, 1, 2),
+        id: 3,
+    };
+    assert_eq!(
+        titles(database.get_posts_before(Some(third), 2).unwrap()),
+        ["Second", "First"]
+    );
+    let second = PostCursor {
+        created_on: time(2023, 1, 2),
+        id: 2,
+    };
+    assert_eq!(
+        titles(database.get_posts_after(second, 2).unwrap()),
+        ["Fourth", "Third"]
+    );
+    assert_eq!(
+        titles(database.get_posts_after(second, 1).unwrap()),
+        ["Third"]
+    );
+}
+
+#[test]
+fn loads_posts_with_their_authors() {
+    let (_file, database) = database_with_author();
+    create_post(&database, "First", time(2023, 1, 1));
+
+    let post = database.get_post_with_author_by_id(1).unwrap().unwrap();
+    assert_eq!(post.post.title, "First");
+    assert_eq!(post.author_username.as_deref(), Some("alice"));
+    assert!(database.get_post_with_author_by_id(2).unwrap().is_none());
+
+    database.remove_user(1, true).unwrap();
+    let post = database.get_post_with_author_by_id(1).unwrap().unwrap();
+    assert_eq!(post.post.author, None);
+    assert_eq!(post.author_username, None);
+    assert_eq!(
+        database.get_posts_before(None, 10).unwrap()[0].author_username,
+        None
+    );
+}
+
+#[test]
+fn searches_posts_without_fulltext_indexes() {
+    let (_file, database) = database_with_author();
+    create_post_with_content(
+        &database,
+        "Rust",
+        time(2023, 1, 1),
+        "Learning RUST with Diesel",
+    );
+    create_post_with_content(
+        &database,
+        "Diesel",
+        time(2023, 2, 1),
+        "Diesel and rust again",
+    );
+    create_post_with_content(&database, "Other", time(2023, 3, 1), "Nothing to see here");
+    create_post_with_content(&database, "Trashed", time(2023, 4, 1), "Rust and Diesel");
+    database
+        .set_post_deleted_on(4, Some(time(2023, 4, 2)))
+        .unwrap();
+
+    let search = |words: &[&str]| PostSearch {
+        words: words.iter().map(ToString::to_string).collect(),
+        ..PostSearch::default()
+    };
+    // regardless of case, and every word has to be there
+    assert_eq!(
+        titles(database.search_posts(&search(&["rust"]), None, 10).unwrap()),
+        ["Diesel", "Rust"]
+    );
+    assert_eq!(
+        titles(
+            database
+                .search_posts(&search(&["learn", "diesel"]), None, 10)
+                .unwrap()
+        ),
+        ["Rust"]
+    );
+    assert!(database
+        .search_posts(&search(&["python"]), None, 10)
+        .unwrap()
+        .is_empty());
+
+    let in_february = PostSearch {
+        from: Some(time(2023, 2, 1)),
+        to: Some(time(2023, 3, 1)),
+        ..search(&["rust"])
+    };
+    assert_eq!(
+        titles(database.search_posts(&in_february, None, 10).unwrap()),
+        ["Diesel"]
+    );
+    let by_someone_else = PostSearch {
+        author: Some(2),
+        ..search(&["rust"])
+    };
+    assert!(database
+        .search_posts(&by_someone_else, None, 10)
+        .unwrap()
+        .is_empty());
+
+    let second = PostCursor {
+        created_on: time(2023, 2, 1),
+        id: 2,
+    };
+    assert_eq!(
+        titles(
+            database
+                .search_posts(&search(&["rust"]), Some(second), 10)
+                .unwrap()
+        ),
+        ["Rust"]
+    );
+    assert_eq!(
+        titles(database.search_posts(&search(&[]), None, 2).unwrap()),
+        ["Other", "Diesel"]
+    );
+}
+
+#[test]
+fn loads_the_revisions_of_all_of_an_authors_posts() {
+    let (_file, database) = database_with_author();
+    create_post(&database, "First", time(2023, 1, 1));
+    create_post(&database, "Second", time(2023, 1, 2));
+    // edited in turns, the revisions still come out by post
+    for (id, title) in [
+        (1, "First edited"),
+        (2, "Second edited"),
+        (1, "First again"),
+    ] {
+        database
+            .edit_post(
+                id,
+                PostEdit {
+                    title,
+                    description: "Description",
+                    content: "Content",
+                    edited_on: time(2023, 1, 3),
+                },
+            )
+            .unwrap();
+    }
+
+    let revisions = database.get_post_revisions_by_author(1).unwrap();
+    let titles: Vec<&str> = revisions
+        .iter()
+        .map(|revision| revision.title.as_str())
+        .collect();
+    assert_eq!(titles, ["First", "First edited", "Second"]);
+    assert!(database.get_post_revisions_by_author(2).unwrap().is_empty());
+}
+
+#[test]
+fn loads_lockouts_with_their_usernames() {
+    let (_file, database) = database_with_author();
+    for (user_id, ip_address, day) in [(Some(1), None, 1), (None, Some("192.0.2.1"), 2)] {
+        database
+            .create_lockout(NewLockout {
+                user_id,
+                ip_address,
+                failed_attempts: 3,
+                locked_on: time(2023, 1, day),
+                locked_until: time(2023, 1, day + 1),
+            })
+            .unwrap();
+    }
+
+    let lockouts = database.get_latest_x_lockouts(10).unwrap();
+    assert_eq!(lockouts.len(), 2);
+    assert_eq!(lockouts[0].lockout.ip_address.as_deref(), Some("192.0.2.1"));
+    assert_eq!(lockouts[0].username, None);
+    assert_eq!(lockouts[1].username.as_deref(), Some("alice"));
+    assert_eq!(database.get_latest_x_lockouts(1).unwrap().len(), 1);
+}
@@ -0,0 +1,56 @@
+use chrono::{Duration, NaiveDate};
+
+use crate::{
+    config::Config,
+    throttle::{LoginThrottle, Refusal, ThrottleKey},
+};
+
+#[test]
+fn lockouts_outlast_a_flood_of_unknown_names() {
+    let throttle = LoginThrottle::new(&Config {
+        login_attempts_before_delay: 100,
+        login_attempts_before_lockout: 2,
+        ..Config::default()
+    });
+    let now = NaiveDate::from_ymd_opt(2023, 3, 20)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap();
+    let address = ThrottleKey::Address("192.0.2.1".to_string());
+    throttle.record_failure(address.clone(), now);
+    assert!(throttle.record_failure(address.clone(), now).is_some());
+
+    // more names than are kept track of, one failure each
+    for name in 0..150_000 {
+        let key = ThrottleKey::UnknownAccount(name.to_string());
+        throttle.record_failure(key, now + Duration::seconds(1));
+    }
+
+    let later = now + Duration::seconds(2);
+    assert!(matches!(
+        throttle.check(&address, later),
+        Some(Refusal::LockedOut { .. })
+    ));
+}
+
+#[test]
+fn forgets_failures_after_a_while() {
+    let config = Config {
+        login_attempts_before_delay: 1,
+        ..Config::default()
+    };
+    let throttle = LoginThrottle::new(&config);
+    let now = NaiveDate::from_ymd_opt(2023, 3, 20)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap();
+    let key = ThrottleKey::UnknownAccount("mallory".to_string());
+    throttle.record_failure(key.clone(), now);
+    assert!(throttle.check(&key, now).is_some());
+
+    throttle.forget_stale(now + config.login_lockout_duration());
+    throttle.record_failure(key.clone(), now + config.login_lockout_duration());
+    // counted as the first failure again, which is delayed by 2 seconds
+    let retry = now + config.login_lockout_duration() + Duration::seconds(2);
+    assert!(throttle.check(&key, retry).is_none());
+}
@@ -0,0 +1,145 @@
+use chrono::Duration;
+use rocket::{http::Status, local::blocking::Client};
+
+use super::{create_post, get_body, logged_in_client, login, logout, post_form, register};
+use crate::application::SharedState;
+
+fn delete(client: &Client) -> String {
+    post_form(client, "/post/1/delete", "")
+        .into_string()
+        .unwrap()
+}
+
+#[test]
+fn deleted_posts_are_gone() {
+    let client = logged_in_client();
+    create_post(&client, "Doomed", "Description", "Some content");
+
+    assert!(delete(&client).contains("Moved the post to the trash."));
+    assert!(!get_body(&client, "/").contains("Doomed"));
+
+    let response = client.get("/post/1").dispatch();
+    assert_eq!(response.status(), Status::Gone);
+    assert!(response
+        .into_string()
+        .unwrap()
+        .contains("This post has been deleted"));
+    assert_eq!(client.get("/post/1/edit").dispatch().status(), Status::Gone);
+}
+
+#[test]
+fn trash_lists_deleted_posts() {
+    let client = logged_in_client();
+    create_post(&client, "Doomed", "Description", "Some content");
+    create_post(&client, "Kept", "Description", "Some content");
+    delete(&client);
+
+    let body = get_body(&client, "/account/trash");
+    assert!(body.contains("Doomed"));
+    assert!(!body.contains("Kept"));
+}
+
+#[test]
+fn restores_a_post() {
+    let client = logged_in_client();
+    create_post(&client, "Doomed", "Description", "Some content");
+    delete(&client);
+
+    let body = post_form(&client, "/account/trash/1/restore", "")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Post restored!"));
+    assert!(get_body(&client, "/").contains("Doomed"));
+    assert_eq!(client.get("/post/1").dispatch().status(), Status::Ok);
+}
+
+#[test]
+fn purges_a_post() {
+    let client = logged_in_client();
+    create_post(&client, "Doomed", "Description", "Some content");
+
+    // only from the trash
+    let body = post_form(&client, "/account/trash/1/purge", "")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("There is no such post in your trash!"));
+
+    delete(&client);
+    let body = post_form(&client, "/account/trash/1/purge", "")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Post deleted for good."));
+    assert!(!body.contains("Doomed"));
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    assert!(database.get_post_by_id(1).unwrap().is_none());
+    assert_eq!(client.get("/post/1").dispatch().status(), Status::NotFound);
+}
+
+#[test]
+fn only_the_author_or_an_admin_can_delete() {
+    let client = logged_in_client();
+    create_post(&client, "Doomed", "Description", "Some content");
+    logout(&client);
+
+    register(&client, "bob", "bob@example.com", "swordfish-2");
+    login(&client, "bob", "swordfish-2");
+    assert!(!get_body(&client, "/post/1").contains("/post/1/delete"));
+    assert!(delete(&client).contains("Only the author can delete this post!"));
+    assert_eq!(client.get("/post/1").dispatch().status(), Status::Ok);
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    database.set_user_admin(2, true).unwrap();
+    assert!(get_body(&client, "/post/1").contains("/post/1/delete"));
+    assert!(delete(&client).contains("Moved the post to the trash."));
+
+    // it ends up in the author's trash, not the admin's
+    assert!(!get_body(&client, "/account/trash").contains("Doomed"));
+    let body = post_form(&client, "/account/trash/1/restore", "")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("There is no such post in your trash!"));
+}
+
+#[test]
+fn posts_without_an_author_are_not_trashed() {
+    let client = logged_in_client();
+    create_post(&client, "Orphaned", "Description", "Some content");
+    logout(&client);
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    database.remove_user(1, true).unwrap();
+    register(&client, "bob", "bob@example.com", "swordfish-2");
+    database.set_user_admin(2, true).unwrap();
+    login(&client, "bob", "swordfish-2");
+
+    assert!(!get_body(&client, "/post/1").contains("/post/1/delete"));
+    assert!(delete(&client).contains("This post has no author whose trash it could go to!"));
+    assert_eq!(client.get("/post/1").dispatch().status(), Status::Ok);
+}
+
+#[test]
+fn old_posts_in_the_trash_are_purged() {
+    let client = logged_in_client();
+    create_post(&client, "Doomed", "Description", "Some content");
+    create_post(&client, "Kept", "Description", "Some content");
+    post_form(
+        &client,
+        "/post/1/edit",
+        "title=Doomed again&description=Description&content=Other content",
+    );
+    delete(&client);
+
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    let now = chrono::offset::Utc::now().naive_utc();
+    assert_eq!(
+        database
+            .remove_posts_deleted_before(now - Duration::days(1))
+            .unwrap(),
+        0
+    );
+    assert_eq!(database.remove_posts_deleted_before(now).unwrap(), 1);
+    assert!(database.get_post_by_id(1).unwrap().is_none());
+    assert!(database.get_post_revisions(1).unwrap().is_empty());
+    assert!(database.get_post_by_id(2).unwrap().is_some());
+}
@@ -0,0 +1,194 @@
+use chrono::NaiveDateTime;
+use rocket::local::blocking::Client;
+
+use super::{client_with_config, get_body, logged_in_client, login, logout, post_form, register};
+use crate::{application::SharedState, totp};
+
+fn secret(client: &Client) -> Vec<u8> {
+    let database = client.rocket().state::<SharedState>().unwrap().database();
+    let user = database.get_user_by_username("alice").unwrap().unwrap();
+    user.totp_secret.unwrap()
+}
+
+// the code an authenticator app would show, `steps` periods from now
+fn code(client: &Client, steps: i64) -> String {
+    let step = chrono::offset::Utc::now().timestamp().div_euclid(30) + steps;
+    format!("{:06}", totp::code_at(&secret(client), step))
+}
+
+fn enter_code(client: &Client, code: &str) -> String {
+    post_form(client, "/login/code", &format!("code={code}"))
+        .into_string()
+        .unwrap()
+}
+
+// turns two-factor authentication on and returns the recovery codes
+fn enable(client: &Client) -> Vec<String> {
+    post_form(client, "/account/two_factor/setup", "");
+    let body = post_form(
+        client,
+        "/account/two_factor/enable",
+        &format!("code={}", code(client, 0)),
+    )
+    .into_string()
+    .unwrap();
+    assert!(body.contains("Two-factor authentication is enabled!"));
+
+    body.split("<li>")
+        .skip(1)
+        .map(|item| item.split("</li>").next().unwrap().trim().to_string())
+        .collect()
+}
+
+#[test]
+fn codes_match_the_rfc_6238_test_vectors() {
+    let secret = b"12345678901234567890";
+    let at = |timestamp| NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap();
+
+    assert_eq!(totp::verify(secret, "287082", at(59)), Some(1));
+    assert_eq!(
+        totp::verify(secret, "081804", at(1_111_111_109)),
+        Some(37_037_036)
+    );
+    assert_eq!(
+        totp::verify(secret, "005 924", at(1_234_567_890)),
+        Some(41_152_263)
+    );
+    assert_eq!(totp::verify(secret, "287082", at(1_234_567_890)), None);
+    assert_eq!(totp::verify(secret, "+87082", at(59)), None);
+}
+
+#[test]
+fn setup_shows_a_qr_code_but_changes_nothing_yet() {
+    let client = logged_in_client();
+    let body = post_form(&client, "/account/two_factor/setup", "")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("data:image/svg+xml;base64,"));
+    assert!(body.contains(&totp::encode_secret(&secret(&client))));
+
+    logout(&client);
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+}
+
+#[test]
+fn enabling_requires_a_valid_code() {
+    let client = logged_in_client();
+    post_form(&client, "/account/two_factor/setup", "");
+    let body = post_form(&client, "/account/two_factor/enable", "code=abcdef")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Invalid code"));
+    assert!(body.contains("data:image/svg+xml;base64,"));
+}
+
+#[test]
+fn login_asks_for_a_code() {
+    let client = logged_in_client();
+    enable(&client);
+    logout(&client);
+
+    let body = login(&client, "alice", "swordfish-2");
+    assert!(body.contains("Code from your authenticator app"));
+    assert!(client.cookies().get("session-key").is_none());
+
+    // the code used to turn it on doesn't work a second time
+    assert!(enter_code(&client, &code(&client, 0)).contains("Invalid code provided."));
+    assert!(client.cookies().get("session-key").is_none());
+
+    let body = enter_code(&client, &code(&client, 1));
+    assert!(body.contains("Logged in successfully!"));
+    assert!(client.cookies().get("session-key").is_some());
+    assert!(client.cookies().get("login-challenge").is_none());
+}
+
+#[test]
+fn code_step_needs_a_password_first() {
+    let client = logged_in_client();
+    enable(&client);
+    logout(&client);
+
+    assert!(get_body(&client, "/login/code").contains("Your login has expired"));
+    assert!(enter_code(&client, &code(&client, 1)).contains("Your login has expired"));
+    assert!(client.cookies().get("session-key").is_none());
+}
+
+#[test]
+fn recovery_codes_work_once() {
+    let client = logged_in_client();
+    let recovery_codes = enable(&client);
+    assert_eq!(recovery_codes.len(), 10);
+    logout(&client);
+
+    login(&client, "alice", "swordfish-2");
+    let body = enter_code(&client, &recovery_codes[0].to_uppercase());
+    assert!(body.contains("Logged in successfully!"));
+    assert!(get_body(&client, "/account/two_factor").contains("9 recovery codes left"));
+    logout(&client);
+
+    login(&client, "alice", "swordfish-2");
+    assert!(enter_code(&client, &recovery_codes[0]).contains("Invalid code provided."));
+}
+
+#[test]
+fn new_recovery_codes_replace_the_old_ones() {
+    let client = logged_in_client();
+    let old_codes = enable(&client);
+
+    let body = post_form(
+        &client,
+        "/account/two_factor/recovery_codes",
+        "password=swordfish-2",
+    )
+    .into_string()
+    .unwrap();
+    assert!(body.contains("Here are your new recovery codes"));
+    logout(&client);
+
+    login(&client, "alice", "swordfish-2");
+    assert!(enter_code(&client, &old_codes[0]).contains("Invalid code provided."));
+}
+
+#[test]
+fn disabling_requires_the_password() {
+    let client = logged_in_client();
+    enable(&client);
+
+    let body = post_form(&client, "/account/two_factor/disable", "password=wrong")
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Password is incorrect!"));
+
+    let body = post_form(
+        &client,
+        "/account/two_factor/disable",
+        "password=swordfish-2",
+    )
+    .into_string()
+    .unwrap();
+    assert!(body.contains("Two-factor authentication is disabled."));
+
+    logout(&client);
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+}
+
+#[test]
+fn guessing_codes_is_throttled() {
+    let figment = rocket::Config::figment()
+        .merge(("login_attempts_before_delay", 10))
+        .merge(("login_attempts_before_lockout", 2));
+    let client = client_with_config(figment);
+    register(&client, "alice", "alice@example.com", "swordfish-2");
+    login(&client, "alice", "swordfish-2");
+    enable(&client);
+    logout(&client);
+
+    login(&client, "alice", "swordfish-2");
+    enter_code(&client, "abcdef");
+    let body = enter_code(&client, "abcdef");
+    assert!(body.contains("logging in is locked"));
+
+    // entering the password again doesn't lift the lockout
+    login(&client, "alice", "swordfish-2");
+    assert!(enter_code(&client, &code(&client, 1)).contains("logging in is locked"));
+}
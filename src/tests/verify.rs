@@ -0,0 +1,80 @@
+use super::{
+    client, client_with_config, get_body, link_in_mail, login, register_unverified, sent_mail,
+};
+
+#[test]
+fn sends_a_verification_link_on_registration() {
+    let client = client();
+    register_unverified(&client, "alice", "alice@example.com", "swordfish-2");
+
+    let mail = sent_mail(&client).pop().unwrap();
+    assert_eq!(mail.to, "alice@example.com");
+    assert!(mail.body.contains("http://localhost:8000/verify/"));
+}
+
+#[test]
+fn verification_link_confirms_the_address() {
+    let client = client();
+    register_unverified(&client, "alice", "alice@example.com", "swordfish-2");
+    let link = link_in_mail(&sent_mail(&client).pop().unwrap(), "/verify/").unwrap();
+
+    let body = client.get(link.as_str()).dispatch().into_string().unwrap();
+    assert!(body.contains("Your e-mail address is confirmed"));
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+
+    // the link can only be used once
+    let body = client.get(link.as_str()).dispatch().into_string().unwrap();
+    assert!(body.contains("This link is invalid or has expired."));
+}
+
+#[test]
+fn rejects_an_invalid_link() {
+    let client = client();
+    assert!(get_body(&client, "/verify/not-hex").contains("This link is invalid"));
+    assert!(get_body(&client, "/verify/00ff").contains("This link is invalid"));
+}
+
+#[test]
+fn login_requires_a_confirmed_address() {
+    let figment = rocket::Config::figment().merge(("email_verification_resend_interval", 0));
+    let client = client_with_config(figment);
+    register_unverified(&client, "alice", "alice@example.com", "swordfish-2");
+    let first_link = link_in_mail(&sent_mail(&client).pop().unwrap(), "/verify/").unwrap();
+
+    let body = login(&client, "alice", "swordfish-2");
+    assert!(body.contains("Please confirm your e-mail address first"));
+    assert!(client.cookies().get("session-key").is_none());
+
+    // a new link is sent and the old one stops working
+    let mails = sent_mail(&client);
+    assert_eq!(mails.len(), 2);
+    let second_link = link_in_mail(&mails[1], "/verify/").unwrap();
+    assert_ne!(first_link, second_link);
+
+    let body = client.get(first_link).dispatch().into_string().unwrap();
+    assert!(body.contains("This link is invalid or has expired."));
+    client.get(second_link).dispatch();
+    assert!(login(&client, "alice", "swordfish-2").contains("Logged in successfully!"));
+}
+
+#[test]
+fn login_does_not_resend_a_recent_link() {
+    let client = client();
+    register_unverified(&client, "alice", "alice@example.com", "swordfish-2");
+
+    for _ in 0..3 {
+        let body = login(&client, "alice", "swordfish-2");
+        assert!(body.contains("check your inbox for the link"));
+    }
+    assert_eq!(sent_mail(&client).len(), 1);
+}
+
+#[test]
+fn wrong_password_does_not_send_a_link() {
+    let client = client();
+    register_unverified(&client, "alice", "alice@example.com", "swordfish-2");
+
+    let body = login(&client, "alice", "wrong");
+    assert!(body.contains("Invalid username/e-mail or password provided."));
+    assert_eq!(sent_mail(&client).len(), 1);
+}
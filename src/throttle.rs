@@ -0,0 +1,211 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::config::Config;
+
+// Names that don't belong to anyone come from whoever is logging in, so there
+// has to be a limit to how many keys are kept track of.
+const MAX_TRACKED_KEYS: usize = 100_000;
+
+#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+pub enum ThrottleKey {
+    // tracked by id, so that alternating between the username and the e-mail
+    // doesn't double the number of guesses
+    Account(i32),
+    // names that don't belong to anyone are throttled just the same, otherwise
+    // the missing delay would reveal which accounts exist
+    UnknownAccount(String),
+    Address(String),
+    // password reset links asked for, by address whether it has an account or not
+    Email(String),
+}
+
+struct Failures {
+    count: u32,
+    last_failure: NaiveDateTime,
+    locked_until: Option<NaiveDateTime>,
+}
+
+// why a login attempt isn't allowed right now
+pub enum Refusal {
+    Delayed { retry_on: NaiveDateTime },
+    LockedOut { until: NaiveDateTime },
+}
+
+impl Refusal {
+    pub fn message(&self, now: NaiveDateTime) -> String {
+        match self {
+            Refusal::Delayed { .. } => format!(
+                "Too many failed login attempts, please wait {} before trying again.",
+                self.wait(now)
+            ),
+            Refusal::LockedOut { .. } => format!(
+                "Too many failed login attempts, logging in is locked for {}.",
+                self.wait(now)
+            ),
+        }
+    }
+
+    // how long until the next attempt is allowed, e.g. "3 seconds"
+    pub fn wait(&self, now: NaiveDateTime) -> String {
+        match self {
+            Refusal::Delayed { retry_on } => count_of((*retry_on - now).num_seconds(), 1, "second"),
+            Refusal::LockedOut { until } => {
+                count_of((*until - now).num_seconds(), 60, "more minute")
+            }
+        }
+    }
+}
+
+// "1 second", "3 seconds" etc., rounded up so that waiting as long as the
+// message says is always enough
+fn count_of(seconds: i64, unit_seconds: i64, unit: &str) -> String {
+    let count = (seconds + unit_seconds - 1) / unit_seconds;
+    if count == 1 {
+        format!("{count} {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
+
+// A lockout that was just started, to be recorded for the administrators.
+pub struct StartedLockout {
+    pub failed_attempts: u32,
+    pub locked_until: NaiveDateTime,
+}
+
+// Counts failed logins per account and per client address. After a few
+// failures every further attempt has to wait twice as long as the previous
+// one, and after too many the account or address is locked out for a while.
+// Requests for password reset links are limited the same way, with every
+// request counting as a failure.
+// Kept in memory, so a restart forgives everyone. Failures nobody has
+// followed up on in a while are forgotten by the periodic cleanup.
+pub struct LoginThrottle {
+    failures: Mutex<HashMap<ThrottleKey, Failures>>,
+    attempts_before_delay: u32,
+    attempts_before_lockout: u32,
+    lockout_duration: Duration,
+}
+
+impl LoginThrottle {
+    pub fn new(config: &Config) -> LoginThrottle {
+        LoginThrottle::with_limits(
+            config.login_attempts_before_delay,
+            config.login_attempts_before_lockout,
+            config.login_lockout_duration(),
+        )
+    }
+
+    pub fn for_password_resets(config: &Config) -> LoginThrottle {
+        LoginThrottle::with_limits(
+            config.password_reset_requests_before_delay,
+            config.password_reset_requests_before_lockout,
+            config.login_lockout_duration(),
+        )
+    }
+
+    fn with_limits(
+        attempts_before_delay: u32,
+        attempts_before_lockout: u32,
+        lockout_duration: Duration,
+    ) -> LoginThrottle {
+        LoginThrottle {
+            failures: Mutex::new(HashMap::new()),
+            attempts_before_delay,
+            attempts_before_lockout,
+            lockout_duration,
+        }
+    }
+
+    // 2 seconds after the first delayed failure, then 4, 8...
+    fn delay(&self, count: u32) -> Duration {
+        let doublings = (count + 1)
+            .saturating_sub(self.attempts_before_delay)
+            .min(30);
+        Duration::seconds(1 << doublings).min(self.lockout_duration)
+    }
+
+    pub fn check(&self, key: &ThrottleKey, now: NaiveDateTime) -> Option<Refusal> {
+        let failures = self.failures.lock().unwrap();
+        let entry = failures.get(key)?;
+
+        if let Some(until) = entry.locked_until.filter(|until| *until > now) {
+            return Some(Refusal::LockedOut { until });
+        }
+
+        if entry.count < self.attempts_before_delay {
+            return None;
+        }
+
+        let retry_on = entry.last_failure + self.delay(entry.count);
+        (retry_on > now).then_some(Refusal::Delayed { retry_on })
+    }
+
+    // Returns the lockout if this failure started one.
+    pub fn record_failure(&self, key: ThrottleKey, now: NaiveDateTime) -> Option<StartedLockout> {
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() >= MAX_TRACKED_KEYS && !failures.contains_key(&key) {
+            self.make_room(&mut failures, now);
+        }
+
+        let entry = failures.entry(key).or_insert(Failures {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        // failures nobody has followed up on in a while don't count anymore
+        if self.is_stale(entry, now) {
+            entry.count = 0;
+        }
+        entry.count += 1;
+        entry.last_failure = now;
+
+        if entry.count < self.attempts_before_lockout {
+            return None;
+        }
+
+        let lockout = StartedLockout {
+            failed_attempts: entry.count,
+            locked_until: now + self.lockout_duration,
+        };
+        // start over once the lockout ends
+        entry.count = 0;
+        entry.locked_until = Some(lockout.locked_until);
+        Some(lockout)
+    }
+
+    pub fn record_success(&self, key: &ThrottleKey) {
+        self.failures.lock().unwrap().remove(key);
+    }
+
+    fn is_stale(&self, entry: &Failures, now: NaiveDateTime) -> bool {
+        entry.locked_until.map_or(true, |until| until <= now)
+            && now - entry.last_failure >= self.lockout_duration
+    }
+
+    pub fn forget_stale(&self, now: NaiveDateTime) {
+        let mut failures = self.failures.lock().unwrap();
+        failures.retain(|_, entry| !self.is_stale(entry, now));
+    }
+
+    // Forgets stale failures and, if that isn't enough, the older half of the
+    // ones that haven't led to a lockout. Halving means this only happens once
+    // in a while even when someone keeps the map full on purpose.
+    fn make_room(&self, failures: &mut HashMap<ThrottleKey, Failures>, now: NaiveDateTime) {
+        failures.retain(|_, entry| !self.is_stale(entry, now));
+        if failures.len() < MAX_TRACKED_KEYS {
+            return;
+        }
+
+        let mut last_failures: Vec<NaiveDateTime> =
+            failures.values().map(|entry| entry.last_failure).collect();
+        let middle = last_failures.len() / 2;
+        let (_, cutoff, _) = last_failures.select_nth_unstable(middle);
+        let cutoff = *cutoff;
+        failures.retain(|_, entry| {
+            entry.locked_until.map_or(false, |until| until > now) || entry.last_failure > cutoff
+        });
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::NaiveDateTime;
+use hmac::{Hmac, Mac};
+use qrcode::{render::svg, QrCode};
+use sha1::Sha1;
+
+use crate::application::{generate_key, Error};
+
+// The parameters every authenticator app assumes when it isn't told
+// otherwise: HMAC-SHA1, six digits and a new code every 30 seconds.
+const DIGITS: usize = 6;
+const MODULUS: u32 = 1_000_000;
+const STEP_SECONDS: i64 = 30;
+// RFC 4226 recommends as many bytes as the HMAC output has
+const SECRET_LENGTH: usize = 20;
+// codes from the step before and after the current one are accepted too, to
+// make up for clocks that are a little off and for slow typing
+const ALLOWED_DRIFT: i64 = 1;
+
+pub fn generate_secret() -> Result<Vec<u8>, Error> {
+    let mut secret = generate_key()?;
+    secret.truncate(SECRET_LENGTH);
+    Ok(secret)
+}
+
+// how the secret is shown to users who can't scan the QR code
+pub fn encode_secret(secret: &[u8]) -> String {
+    data_encoding::BASE32_NOPAD.encode(secret)
+}
+
+fn time_step(time: NaiveDateTime) -> i64 {
+    time.timestamp().div_euclid(STEP_SECONDS)
+}
+
+// the HOTP value of RFC 4226 for the given counter
+pub fn code_at(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+    let truncated = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    truncated % MODULUS
+}
+
+// The time step the code belongs to, if it is a valid one right now. Callers
+// have to make sure the same step isn't accepted twice.
+pub fn verify(secret: &[u8], code: &str, now: NaiveDateTime) -> Option<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != DIGITS || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+
+    let current_step = time_step(now);
+    (current_step - ALLOWED_DRIFT..=current_step + ALLOWED_DRIFT)
+        .find(|&step| code_at(secret, step) == code)
+}
+
+// anything outside of the unreserved characters of RFC 3986 gets escaped
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+// the otpauth:// URI authenticator apps read out of the QR code
+pub fn provisioning_uri(issuer: &str, account: &str, secret: &[u8]) -> String {
+    let issuer = percent_encode(issuer);
+    format!(
+        "otpauth://totp/{issuer}:{}?secret={}&issuer={issuer}",
+        percent_encode(account),
+        encode_secret(secret)
+    )
+}
+
+// Rendered on the server, so that the secret never goes to a third party, and
+// handed to the page as a data URI for an <img>.
+pub fn qr_code_svg(uri: &str) -> Result<String, Error> {
+    let code = QrCode::new(uri).map_err(|error| Error::QrCode(error.to_string()))?;
+    let svg = code.render::<svg::Color>().min_dimensions(200, 200).build();
+    Ok(format!(
+        "data:image/svg+xml;base64,{}",
+        data_encoding::BASE64.encode(svg.as_bytes())
+    ))
+}
@@ -0,0 +1,208 @@
+use rocket::{get, http::CookieJar, post, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{BaseLayoutContext, Error, ErrorResponder, SharedState},
+    csrf::{CsrfForm, CsrfTokenForm},
+    models::{Post, User},
+};
+
+#[derive(Serialize, Debug)]
+struct TrashedPostData {
+    id: i32,
+    title: String,
+    description: String,
+    human_readable_deletion_time: String,
+    // when the periodic cleanup will remove it for good
+    human_readable_purge_time: String,
+}
+
+impl TrashedPostData {
+    // None for posts that aren't in the trash
+    fn from_post(state: &SharedState, post: Post) -> Option<Self> {
+        let deleted_on = post.deleted_on?;
+        let purged_on = deleted_on + state.config().deleted_post_retention();
+
+        Some(Self {
+            id: post.id,
+            title: post.title,
+            description: post.description,
+            human_readable_deletion_time: deleted_on.format("%d. %m. %Y. %H:%M:%S").to_string(),
+            human_readable_purge_time: purged_on.format("%d. %m. %Y. %H:%M:%S").to_string(),
+        })
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct TrashLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    posts: Vec<TrashedPostData>,
+    error: Option<String>,
+    success: Option<String>,
+}
+
+impl TrashLayoutContext {
+    pub fn new(state: &State<SharedState>, jar: &CookieJar) -> Result<TrashLayoutContext, Error> {
+        let (posts, error) = match state.get_valid_user_info(jar)? {
+            Some(user) => (
+                state
+                    .database()
+                    .get_deleted_posts_by_author(user.id)?
+                    .into_iter()
+                    .filter_map(|post| TrashedPostData::from_post(state, post))
+                    .collect(),
+                None,
+            ),
+            None => (Vec::new(), Some("You need to log in first!".to_string())),
+        };
+
+        Ok(TrashLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            posts,
+            error,
+            success: None,
+        })
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_success(mut self, success: Option<String>) -> Self {
+        self.success = success;
+        self
+    }
+}
+
+// a post from the user's own trash, anything else is treated as missing
+fn trashed_post(state: &SharedState, user: &User, id: i32) -> Result<Option<Post>, Error> {
+    Ok(state
+        .database()
+        .get_post_by_id(id)?
+        .filter(|post| post.author == Some(user.id) && post.deleted_on.is_some()))
+}
+
+#[get("/account/trash")]
+pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "trash",
+        TrashLayoutContext::new(state, jar)?,
+    ))
+}
+
+// Moves a post to its author's trash. Admins can delete anyone's posts, the
+// post still ends up in the trash of whoever wrote it, so posts whose author
+// is gone can't be deleted this way as nobody could restore them.
+#[post("/post/<id>/delete", data = "<_csrf>")]
+pub fn delete(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    id: i32,
+    _csrf: CsrfForm<CsrfTokenForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        let post = state
+            .database()
+            .get_post_by_id(id)?
+            .ok_or(Error::InvalidPostId)?;
+        if post.author != Some(user.id) && !user.is_admin {
+            break 'requirements Some("Only the author can delete this post!");
+        }
+        if post.deleted_on.is_some() {
+            break 'requirements Some("This post is already in the trash!");
+        }
+        if post.author.is_none() {
+            break 'requirements Some("This post has no author whose trash it could go to!");
+        }
+
+        state
+            .database()
+            .set_post_deleted_on(id, Some(chrono::offset::Utc::now().naive_utc()))?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "trash",
+            TrashLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "trash",
+        TrashLayoutContext::new(state, jar)?
+            .with_success(Some("Moved the post to the trash.".to_string())),
+    ))
+}
+
+#[post("/account/trash/<id>/restore", data = "<_csrf>")]
+pub fn restore(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    id: i32,
+    _csrf: CsrfForm<CsrfTokenForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        if trashed_post(state, &user, id)?.is_none() {
+            break 'requirements Some("There is no such post in your trash!");
+        }
+
+        state.database().set_post_deleted_on(id, None)?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "trash",
+            TrashLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "trash",
+        TrashLayoutContext::new(state, jar)?.with_success(Some("Post restored!".to_string())),
+    ))
+}
+
+#[post("/account/trash/<id>/purge", data = "<_csrf>")]
+pub fn purge(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    id: i32,
+    _csrf: CsrfForm<CsrfTokenForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        // only posts already in the trash, so nothing is lost with one click
+        if trashed_post(state, &user, id)?.is_none() {
+            break 'requirements Some("There is no such post in your trash!");
+        }
+
+        state.database().remove_post(id)?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "trash",
+            TrashLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "trash",
+        TrashLayoutContext::new(state, jar)?
+            .with_success(Some("Post deleted for good.".to_string())),
+    ))
+}
@@ -0,0 +1,359 @@
+use chrono::NaiveDateTime;
+use rocket::{get, http::CookieJar, post, FromForm, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{
+        generate_key, token_digest, BaseLayoutContext, Error, ErrorResponder, SharedState,
+    },
+    csrf::{CsrfForm, CsrfProtected, CsrfTokenForm},
+    models::{NewRecoveryCode, User},
+    totp,
+};
+
+const RECOVERY_CODE_COUNT: usize = 10;
+// ten hexadecimal digits each
+const RECOVERY_CODE_BYTES: usize = 5;
+
+#[derive(Serialize, Debug)]
+struct TwoFactorSetup {
+    // data URI of the QR code for the authenticator app
+    qr_code: String,
+    // the same secret for typing in by hand
+    secret: String,
+}
+
+#[derive(Serialize, Debug)]
+struct TwoFactorLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    enabled: bool,
+    recovery_codes_left: i64,
+    // set while a new secret is waiting to be confirmed with a first code
+    setup: Option<TwoFactorSetup>,
+    // only ever shown right after they were generated
+    recovery_codes: Option<Vec<String>>,
+    error: Option<String>,
+    success: Option<String>,
+}
+
+impl TwoFactorLayoutContext {
+    pub fn new(
+        state: &State<SharedState>,
+        jar: &CookieJar,
+    ) -> Result<TwoFactorLayoutContext, Error> {
+        let mut context = TwoFactorLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            enabled: false,
+            recovery_codes_left: 0,
+            setup: None,
+            recovery_codes: None,
+            error: None,
+            success: None,
+        };
+
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            context.error = Some("You need to log in first!".to_string());
+            return Ok(context);
+        };
+
+        context.enabled = user.totp_enabled;
+        context.recovery_codes_left = state.database().get_recovery_code_count(user.id)?;
+        if let Some(secret) = user.totp_secret.filter(|_| !user.totp_enabled) {
+            let uri = totp::provisioning_uri(&state.config().totp_issuer, &user.username, &secret);
+            context.setup = Some(TwoFactorSetup {
+                qr_code: totp::qr_code_svg(&uri)?,
+                secret: totp::encode_secret(&secret),
+            });
+        }
+
+        Ok(context)
+    }
+
+    pub fn with_recovery_codes(mut self, recovery_codes: Option<Vec<String>>) -> Self {
+        self.recovery_codes = recovery_codes;
+        self
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_success(mut self, success: Option<String>) -> Self {
+        self.success = success;
+        self
+    }
+}
+
+// Only the digest is stored, like with session keys. Codes are compared
+// without the dash and regardless of case, however they were typed in.
+fn recovery_code_digest(code: &str) -> Vec<u8> {
+    let code: String = code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|character| character.to_ascii_lowercase())
+        .collect();
+    token_digest(code.as_bytes())
+}
+
+// Replaces all of the user's recovery codes with new ones and returns them in
+// the form they are shown in, e.g. 1a2b3-c4d5e.
+fn generate_recovery_codes(state: &SharedState, user_id: i32) -> Result<Vec<String>, Error> {
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let mut key = generate_key()?;
+        key.truncate(RECOVERY_CODE_BYTES);
+        let code = hex::encode(key);
+        codes.push(format!("{}-{}", &code[..5], &code[5..]));
+    }
+
+    state.database().remove_recovery_codes_by_user_id(user_id)?;
+    state.database().create_recovery_codes(
+        codes
+            .iter()
+            .map(|code| NewRecoveryCode {
+                user_id,
+                code_digest: recovery_code_digest(code),
+            })
+            .collect(),
+    )?;
+
+    Ok(codes)
+}
+
+// Whether the code from the authenticator app or one of the recovery codes is
+// right. Either way it is used up and won't be accepted a second time.
+pub fn check_code(
+    state: &SharedState,
+    user: &User,
+    code: &str,
+    now: NaiveDateTime,
+) -> Result<bool, Error> {
+    let Some(secret) = user.totp_secret.as_ref().filter(|_| user.totp_enabled) else {
+        return Ok(false);
+    };
+
+    if let Some(step) = totp::verify(secret, code, now) {
+        return state.database().advance_user_totp_step(user.id, step);
+    }
+
+    state
+        .database()
+        .remove_recovery_code(user.id, recovery_code_digest(code))
+}
+
+#[get("/account/two_factor")]
+pub fn get(state: &State<SharedState>, jar: &CookieJar) -> Result<Template, ErrorResponder> {
+    Ok(Template::render(
+        "two_factor",
+        TwoFactorLayoutContext::new(state, jar)?,
+    ))
+}
+
+// Stores a new secret and shows it as a QR code. Nothing changes at login
+// until the secret is confirmed with a code from the app.
+#[allow(clippy::needless_pass_by_value)]
+#[post("/account/two_factor/setup", data = "<_csrf>")]
+pub fn post_setup(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    _csrf: CsrfForm<CsrfTokenForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        if user.totp_enabled {
+            break 'requirements Some("Two-factor authentication is already enabled!");
+        }
+
+        let secret = totp::generate_secret()?;
+        state
+            .database()
+            .set_user_totp_secret(user.id, Some(&secret))?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "two_factor",
+            TwoFactorLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "two_factor",
+        TwoFactorLayoutContext::new(state, jar)?,
+    ))
+}
+
+#[derive(FromForm)]
+pub struct EnableTwoFactorForm {
+    pub csrf_token: String,
+    pub code: String,
+}
+
+impl CsrfProtected for EnableTwoFactorForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/account/two_factor/enable", data = "<data>")]
+pub fn post_enable(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    data: CsrfForm<EnableTwoFactorForm>,
+) -> Result<Template, ErrorResponder> {
+    let mut recovery_codes = None;
+
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        if user.totp_enabled {
+            break 'requirements Some("Two-factor authentication is already enabled!");
+        }
+        let Some(secret) = &user.totp_secret else {
+            break 'requirements Some("Start the setup first!")
+        };
+        if data.code.is_empty() {
+            break 'requirements Some("All fields are required!");
+        }
+
+        // proves that the app was set up with the right secret
+        let now = chrono::offset::Utc::now().naive_utc();
+        let Some(step) = totp::verify(secret, &data.code, now) else {
+            break 'requirements Some("Invalid code, make sure the time on your device is right.")
+        };
+
+        state.database().advance_user_totp_step(user.id, step)?;
+        state.database().set_user_totp_enabled(user.id)?;
+        recovery_codes = Some(generate_recovery_codes(state, user.id)?);
+
+        None
+    } {
+        return Ok(Template::render(
+            "two_factor",
+            TwoFactorLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "two_factor",
+        TwoFactorLayoutContext::new(state, jar)?
+            .with_recovery_codes(recovery_codes)
+            .with_success(Some(
+                "Two-factor authentication is enabled! Keep the recovery codes below \
+                somewhere safe, each of them can be used once instead of a code."
+                    .to_string(),
+            )),
+    ))
+}
+
+#[derive(FromForm)]
+pub struct TwoFactorPasswordForm {
+    pub csrf_token: String,
+    pub password: String,
+}
+
+impl CsrfProtected for TwoFactorPasswordForm {
+    fn csrf_token(&self) -> &str {
+        &self.csrf_token
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/account/two_factor/recovery_codes", data = "<data>")]
+pub fn post_recovery_codes(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    data: CsrfForm<TwoFactorPasswordForm>,
+) -> Result<Template, ErrorResponder> {
+    let mut recovery_codes = None;
+
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        if !user.totp_enabled {
+            break 'requirements Some("Two-factor authentication is not enabled!");
+        }
+        if data.password.is_empty() {
+            break 'requirements Some("All fields are required!");
+        }
+        if !state
+            .password_hasher()
+            .verify(&data.password, &user.password)?
+        {
+            break 'requirements Some("Password is incorrect!");
+        }
+
+        recovery_codes = Some(generate_recovery_codes(state, user.id)?);
+
+        None
+    } {
+        return Ok(Template::render(
+            "two_factor",
+            TwoFactorLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "two_factor",
+        TwoFactorLayoutContext::new(state, jar)?
+            .with_recovery_codes(recovery_codes)
+            .with_success(Some(
+                "Here are your new recovery codes, the old ones no longer work.".to_string(),
+            )),
+    ))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[post("/account/two_factor/disable", data = "<data>")]
+pub fn post_disable(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    data: CsrfForm<TwoFactorPasswordForm>,
+) -> Result<Template, ErrorResponder> {
+    if let Some(error_message) = 'requirements: {
+        let Some(user) = state.get_valid_user_info(jar)? else {
+            break 'requirements Some("You need to log in first!")
+        };
+
+        if !user.totp_enabled {
+            break 'requirements Some("Two-factor authentication is not enabled!");
+        }
+        if data.password.is_empty() {
+            break 'requirements Some("All fields are required!");
+        }
+        // a session left open on a shared computer shouldn't be enough
+        if !state
+            .password_hasher()
+            .verify(&data.password, &user.password)?
+        {
+            break 'requirements Some("Password is incorrect!");
+        }
+
+        state.database().set_user_totp_secret(user.id, None)?;
+        state.database().remove_recovery_codes_by_user_id(user.id)?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "two_factor",
+            TwoFactorLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "two_factor",
+        TwoFactorLayoutContext::new(state, jar)?
+            .with_success(Some("Two-factor authentication is disabled.".to_string())),
+    ))
+}
@@ -0,0 +1,85 @@
+use crate::config::Config;
+
+// bcrypt silently ignores everything past this many bytes, so two passwords
+// that only differ after that would both be accepted. Enforced with Argon2id
+// as well, since passwords get rehashed with bcrypt if it is configured again.
+const BCRYPT_MAX_BYTES: usize = 72;
+
+// same as the width of the e-mail column
+const EMAIL_MAX_LENGTH: usize = 255;
+
+// lowercase, one per line
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+fn length_in_characters(value: &str) -> usize {
+    value.chars().count()
+}
+
+pub fn validate_username(config: &Config, username: &str) -> Result<(), String> {
+    let length = length_in_characters(username);
+    if length < config.username_min_length || length > config.username_max_length {
+        return Err(format!(
+            "Usernames have to be between {} and {} characters long.",
+            config.username_min_length, config.username_max_length
+        ));
+    }
+
+    let allowed = |character: char| {
+        character.is_ascii_alphanumeric() || config.username_allowed_symbols.contains(character)
+    };
+    if !username.chars().all(allowed) {
+        return Err(if config.username_allowed_symbols.is_empty() {
+            "Usernames can only contain letters and digits.".to_string()
+        } else {
+            format!(
+                "Usernames can only contain letters, digits and these symbols: {}",
+                config.username_allowed_symbols
+            )
+        });
+    }
+
+    Ok(())
+}
+
+pub fn validate_email(email: &str) -> Result<(), String> {
+    if length_in_characters(email) > EMAIL_MAX_LENGTH {
+        return Err(format!(
+            "E-mail addresses can be at most {EMAIL_MAX_LENGTH} characters long."
+        ));
+    }
+
+    // anything more thorough is up to the verification e-mail
+    match email.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && !domain.is_empty() => Ok(()),
+        _ => Err("That doesn't look like an e-mail address.".to_string()),
+    }
+}
+
+pub fn validate_password(config: &Config, password: &str) -> Result<(), String> {
+    let length = length_in_characters(password);
+    if length < config.password_min_length {
+        return Err(format!(
+            "Passwords have to be at least {} characters long.",
+            config.password_min_length
+        ));
+    }
+    if length > config.password_max_length {
+        return Err(format!(
+            "Passwords can be at most {} characters long.",
+            config.password_max_length
+        ));
+    }
+    if password.len() > BCRYPT_MAX_BYTES {
+        return Err(format!(
+            "Passwords can be at most {BCRYPT_MAX_BYTES} bytes long, \
+            accented letters and other special characters take up more than one."
+        ));
+    }
+
+    let password = password.to_lowercase();
+    if COMMON_PASSWORDS.lines().any(|common| common == password) {
+        return Err("This password is too common, please pick another one.".to_string());
+    }
+
+    Ok(())
+}
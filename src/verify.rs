@@ -0,0 +1,210 @@
+use rocket::{get, http::CookieJar, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+use crate::{
+    application::{
+        generate_key, token_digest, BaseLayoutContext, Error, ErrorResponder, SharedState,
+    },
+    mail::Email,
+    models::{NewEmailVerification, User},
+};
+
+// Sends a link for confirming an e-mail address. With `new_email` set, the
+// link goes to that address instead and switches the account over to it once
+// opened. Links sent earlier stop working, so only the latest e-mail is ever of
+// any use.
+fn send_verification_link(
+    state: &SharedState,
+    user: &User,
+    new_email: Option<&str>,
+) -> Result<(), Error> {
+    let token = generate_key()?;
+
+    state
+        .database()
+        .remove_email_verifications_by_user_id(user.id)?;
+    state
+        .database()
+        .create_email_verification(NewEmailVerification {
+            token_digest: token_digest(&token),
+            user_id: user.id,
+            expires_on: chrono::offset::Utc::now().naive_utc()
+                + state.config().email_verification_lifetime(),
+            new_email,
+        })?;
+
+    let link = format!(
+        "{}/verify/{}",
+        state.config().public_url,
+        hex::encode(token)
+    );
+    let (to, subject, body) = match new_email {
+        None => (
+            user.email.clone(),
+            "Confirm your e-mail address",
+            format!(
+                "Hello {},\n\n\
+                please confirm your e-mail address by opening the following link:\n\n\
+                {link}\n\n\
+                If you didn't register an account, you can ignore this e-mail.",
+                user.username
+            ),
+        ),
+        Some(new_email) => (
+            new_email.to_string(),
+            "Confirm your new e-mail address",
+            format!(
+                "Hello {},\n\n\
+                please confirm your new e-mail address by opening the following link:\n\n\
+                {link}\n\n\
+                Until then, your account keeps using the old address. If you didn't ask \
+                for this, you can ignore this e-mail.",
+                user.username
+            ),
+        ),
+    };
+
+    state.mailer().send(&Email {
+        to,
+        subject: subject.to_string(),
+        body,
+    })
+}
+
+// confirms the address the user registered with
+pub fn send_verification_email(state: &SharedState, user: &User) -> Result<(), Error> {
+    send_verification_link(state, user, None)
+}
+
+// confirms an address the user wants to switch to
+pub fn send_email_change_email(
+    state: &SharedState,
+    user: &User,
+    new_email: &str,
+) -> Result<(), Error> {
+    send_verification_link(state, user, Some(new_email))
+}
+
+// Sent instead of a verification link when somebody registers with an e-mail
+// address that is already in use, so that only its owner learns about it.
+pub fn send_already_registered_email(state: &SharedState, user: &User) -> Result<(), Error> {
+    state.mailer().send(&Email {
+        to: user.email.clone(),
+        subject: "Somebody tried to register with your e-mail address".to_string(),
+        body: format!(
+            "Hello {},\n\n\
+            somebody tried to register a new account with your e-mail address, \
+            but you already have an account. If that was you, you can log in at:\n\n\
+            {}/login\n\n\
+            Otherwise you can ignore this e-mail.",
+            user.username,
+            state.config().public_url
+        ),
+    })
+}
+
+// for when somebody tries to register a name an unconfirmed account holds
+pub fn send_username_taken_email(state: &SharedState, user: &User) -> Result<(), Error> {
+    state.mailer().send(&Email {
+        to: user.email.clone(),
+        subject: "Somebody tried to register your username".to_string(),
+        body: format!(
+            "Hello {},\n\n\
+            somebody tried to register a new account with your username, but your \
+            account still holds it. It is waiting for you to confirm your e-mail \
+            address, logging in sends you a new link if the first one got lost:\n\n\
+            {}/login\n\n\
+            Otherwise you can ignore this e-mail.",
+            user.username,
+            state.config().public_url
+        ),
+    })
+}
+
+#[derive(Serialize, Debug)]
+struct VerifyLayoutContext {
+    #[serde(flatten)]
+    base_context: BaseLayoutContext,
+    error: Option<String>,
+    success: Option<String>,
+}
+
+impl VerifyLayoutContext {
+    pub fn new(state: &State<SharedState>, jar: &CookieJar) -> Result<VerifyLayoutContext, Error> {
+        Ok(VerifyLayoutContext {
+            base_context: BaseLayoutContext::new(state, jar)?,
+            error: None,
+            success: None,
+        })
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub fn with_success(mut self, success: Option<String>) -> Self {
+        self.success = success;
+        self
+    }
+}
+
+const INVALID_LINK: &str = "This link is invalid or has expired.";
+
+#[get("/verify/<token>")]
+pub fn get(
+    state: &State<SharedState>,
+    jar: &CookieJar,
+    token: &str,
+) -> Result<Template, ErrorResponder> {
+    let mut success_message = "Your e-mail address is confirmed, you can log in now!";
+
+    if let Some(error_message) = 'requirements: {
+        let Ok(token) = hex::decode(token) else {
+            break 'requirements Some(INVALID_LINK)
+        };
+
+        let digest = token_digest(&token);
+        let Some(verification) = state.database().get_email_verification_by_token(digest)? else {
+            break 'requirements Some(INVALID_LINK)
+        };
+
+        if verification.expires_on <= chrono::offset::Utc::now().naive_utc() {
+            break 'requirements Some(INVALID_LINK);
+        }
+
+        if let Some(new_email) = &verification.new_email {
+            // the address could have been taken since the link was sent out
+            match state
+                .database()
+                .set_user_email(verification.user_id, new_email)
+            {
+                Err(error) if error.is_unique_violation() => {
+                    break 'requirements Some("This e-mail address is already in use!");
+                }
+                result => result?,
+            }
+            success_message = "Your e-mail address has been changed!";
+        }
+
+        state
+            .database()
+            .set_user_email_verified(verification.user_id)?;
+        state
+            .database()
+            .remove_email_verifications_by_user_id(verification.user_id)?;
+
+        None
+    } {
+        return Ok(Template::render(
+            "verify",
+            VerifyLayoutContext::new(state, jar)?.with_error(Some(error_message.to_owned())),
+        ));
+    }
+
+    Ok(Template::render(
+        "verify",
+        VerifyLayoutContext::new(state, jar)?.with_success(Some(success_message.to_string())),
+    ))
+}